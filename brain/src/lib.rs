@@ -7,22 +7,46 @@ use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+use differential_drive::CommandId;
 use differential_drive::DriveCmd;
+use differential_drive::Notifier;
+use lidar::PowerState;
+use lidar::RangeScanner;
 
 use log::*;
 
-use differential_drive::Drive;
-use lidar::Lidar;
-
-use crate::brain::simple::Simple;
+use crate::simple::Simple;
 
 mod simple;
 
+// The brain only needs to send drive commands and read lidar frames, on the rover these are
+// `differential_drive::Drive` and a `lidar::RangeScanner`, the simulator provides its own.
+pub trait DriveControl: Clone + Send + 'static {
+    fn send(&self, cmd: DriveCmd) -> Result<CommandId, SendError<DriveCmd>>;
+    fn is_active(&self) -> bool;
+    fn notifier(&self) -> &Notifier;
+}
+
+impl DriveControl for differential_drive::Drive<'static> {
+    fn send(&self, cmd: DriveCmd) -> Result<CommandId, SendError<DriveCmd>> {
+        differential_drive::Drive::send(self, cmd)
+    }
+
+    fn is_active(&self) -> bool {
+        differential_drive::Drive::is_active(self)
+    }
+
+    fn notifier(&self) -> &Notifier {
+        differential_drive::Drive::notifier(self)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum BrainCmd {
     State(bool), // on/off
     Move(i64),
     Rotate(i64),
+    Pivot(i64), // see DriveCmd::Pivot, the rover turns by half of it
     Left(i64),
     Right(i64),
     LidarOnOff(bool),
@@ -34,7 +58,10 @@ pub struct Brain {
 }
 
 impl Brain {
-    pub fn new(mut lidar: Lidar<'static>, drive: Drive<'static>) -> Result<Brain, std::io::Error> {
+    pub fn new(
+        mut lidar: impl RangeScanner,
+        drive: impl DriveControl,
+    ) -> Result<Brain, std::io::Error> {
         let active = Arc::new(AtomicBool::new(false));
         let (tx, cmd_rx) = channel();
         {
@@ -44,6 +71,12 @@ impl Brain {
                 .name("brain".into())
                 .spawn(move || {
                     let simpleton = Simple::start(brain_tx, drive.clone()).unwrap();
+                    {
+                        let simpleton = simpleton.clone();
+                        lidar.power_status().on_change(move |state| {
+                            let _ = simpleton.send(simple::Event::Lidar(state));
+                        });
+                    }
                     loop {
                         if let Ok(cmd) = cmd_rx.recv_timeout(Duration::from_millis(250)) {
                             match cmd {
@@ -76,6 +109,16 @@ impl Brain {
                                         error!("failed to send rotate command: {err}");
                                     }
                                 }
+                                BrainCmd::Pivot(degrees) => {
+                                    let drive_cmd = match degrees {
+                                        0 => DriveCmd::Stop,
+                                        _ => DriveCmd::Pivot(degrees),
+                                    };
+                                    info!("pivot({drive_cmd:?})");
+                                    if let Err(err) = drive.send(drive_cmd) {
+                                        error!("failed to send pivot command: {err}");
+                                    }
+                                }
                                 BrainCmd::Left(distance) => {
                                     let drive_cmd = match distance {
                                         0 => DriveCmd::Stop,
@@ -102,9 +145,14 @@ impl Brain {
                                 }
                             }
                         }
-                        simpleton
-                            .send(simple::Event::Frame(lidar.get_frame()))
-                            .unwrap();
+                        // the ranges are only meaningful while the lidar is synced
+                        if lidar.power_status().state() == PowerState::Synced {
+                            simpleton
+                                .send(simple::Event::Frame(
+                                    lidar.get_frame(lidar::DEFAULT_SECTORS),
+                                ))
+                                .unwrap();
+                        }
                         // if !active.load(Ordering::Relaxed) {
                         //     continue;
                         // }
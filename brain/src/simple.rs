@@ -0,0 +1,509 @@
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::SendError;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use differential_drive::CommandId;
+use differential_drive::Completion;
+use differential_drive::DriveCmd;
+use lidar::Frame;
+use lidar::PowerState;
+use log::*;
+
+use super::BrainCmd;
+use super::DriveControl;
+
+// longest wait for the lidar to sync after power on
+const WARMUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+// the rover keeps going for a while after a Stop, frames until then don't show where it ends up
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub(super) enum Event {
+    Start,
+    Frame(Frame),
+    Stop,
+    Timeout,
+    Done(Completion), // a drive command finished
+    Lidar(PowerState),
+}
+
+#[derive(Debug)]
+enum State {
+    Idle,
+    Warmup,
+    SearchChoice,
+    Searching,
+    Moving,
+    Stopping,
+    Found,
+}
+
+#[allow(dead_code)]
+pub(super) struct Simple<D: DriveControl> {
+    brain: Sender<BrainCmd>,
+    drive: D,
+    state: State,
+    timeout: Option<Instant>,
+    pending: Option<CommandId>, // the last drive command
+    lidar: PowerState,
+    tx: Sender<Event>,
+    rx: Receiver<Event>,
+}
+
+impl<D: DriveControl> Simple<D> {
+    pub fn start(brain: Sender<BrainCmd>, drive: D) -> Result<Sender<Event>, BrainError> {
+        let (tx, rx) = channel();
+        {
+            let tx = tx.clone();
+            drive.notifier().on_complete(move |completion| {
+                let _ = tx.send(Event::Done(completion));
+            });
+        }
+        let mut simple = Simple {
+            brain,
+            drive,
+            state: State::Idle,
+            timeout: None,
+            pending: None,
+            lidar: PowerState::Off,
+            tx: tx.clone(),
+            rx,
+        };
+        thread::Builder::new()
+            .stack_size(8192)
+            .name("simpleton".into())
+            .spawn(move || {
+                info!("starting loop");
+                loop {
+                    let event = simple.next_event();
+                    if let Err(err) = simple.event(event).and_then(|_| simple.run()) {
+                        error!("simpleton stopped: {err}");
+                        return;
+                    }
+                }
+            })?;
+        Ok(tx)
+    }
+
+    fn next_event(&mut self) -> Event {
+        match self.timeout {
+            Some(deadline) => {
+                let wait = deadline.saturating_duration_since(Instant::now());
+                match self.rx.recv_timeout(wait) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.timeout = None;
+                        Event::Timeout
+                    }
+                    Err(RecvTimeoutError::Disconnected) => panic!("simple: event channel closed"),
+                }
+            }
+            None => self.rx.recv().unwrap(),
+        }
+    }
+
+    fn drive(&mut self, cmd: DriveCmd) -> Result<(), BrainError> {
+        self.pending = Some(self.drive.send(cmd)?);
+        Ok(())
+    }
+
+    fn is_pending(&self, completion: &Completion) -> bool {
+        self.pending == Some(completion.id)
+    }
+
+    // stop and wait in Warmup for the lidar to sync again
+    fn lidar_lost(&mut self) -> Result<(), BrainError> {
+        self.drive(DriveCmd::Stop)?;
+        self.after(WARMUP_TIMEOUT);
+        self.state = State::Warmup;
+        Ok(())
+    }
+
+    // stop and go to `state` once the rover has settled
+    fn stop_and_settle(&mut self, state: State) -> Result<(), BrainError> {
+        self.drive(DriveCmd::Stop)?;
+        self.after(SETTLE_TIME);
+        self.state = state;
+        Ok(())
+    }
+
+    fn after(&mut self, duration: Duration) {
+        self.timeout = Some(Instant::now() + duration);
+    }
+
+    pub fn event(&mut self, event: Event) -> Result<(), BrainError> {
+        if let Event::Lidar(state) = event {
+            self.lidar = state;
+        }
+        match self.state {
+            #[allow(clippy::single_match)]
+            State::Idle => match event {
+                Event::Start if self.lidar == PowerState::Synced => {
+                    info!("Idle received Start, lidar already synced");
+                    self.state = State::SearchChoice;
+                }
+                Event::Start => {
+                    info!("Idle received Start");
+                    self.brain.send(BrainCmd::LidarOnOff(true))?;
+                    self.after(WARMUP_TIMEOUT);
+                    self.state = State::Warmup;
+                }
+                _ => {}
+            },
+            State::Warmup => match event {
+                Event::Stop => {
+                    info!("Warmup received Stop");
+                    self.timeout = None;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                Event::Lidar(PowerState::Synced) => {
+                    info!("Warmup: lidar synced");
+                    self.timeout = None;
+                    self.state = State::SearchChoice;
+                }
+                Event::Timeout => {
+                    info!("Warmup received Timeout, lidar didn't sync");
+                    self.brain.send(BrainCmd::LidarOnOff(false))?;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                _ => {}
+            },
+            State::SearchChoice => match event {
+                Event::Stop => {
+                    info!("SearchChoice received Stop");
+                    self.brain.send(BrainCmd::LidarOnOff(false))?;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                Event::Lidar(state) if state != PowerState::Synced => {
+                    info!("SearchChoice: lidar {state:?}, stop and wait for it");
+                    self.lidar_lost()?;
+                }
+                Event::Frame(frame) => {
+                    let (left, front, right) = ranges(&frame);
+                    if let Some(front) = further(front, 2000) {
+                        self.display_ranges(&frame);
+                        info!("SearchChoice: no search, just go!");
+                        self.drive(DriveCmd::Move(front as i64))?;
+                        self.state = State::Moving;
+                    } else if left.unwrap_or(0) > right.unwrap_or(0) {
+                        self.display_ranges(&frame);
+                        info!("SearchChoice: search left");
+                        self.drive(DriveCmd::Rotate(-270))?;
+                        self.state = State::Searching;
+                    } else {
+                        self.display_ranges(&frame);
+                        info!("SearchChoice: search right");
+                        self.drive(DriveCmd::Rotate(270))?;
+                        self.state = State::Searching;
+                    }
+                }
+                _ => {}
+            },
+            State::Searching => match event {
+                Event::Stop => {
+                    info!("Searching received Stop");
+                    self.brain.send(BrainCmd::LidarOnOff(false))?;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                Event::Lidar(state) if state != PowerState::Synced => {
+                    info!("Searching: lidar {state:?}, stop and wait for it");
+                    self.lidar_lost()?;
+                }
+                Event::Frame(frame) => {
+                    let (left, front, right) = ranges(&frame);
+                    if closer(left, 100) || closer(right, 100) {
+                        self.display_ranges(&frame);
+                        info!("Searching: too close, stop");
+                        self.stop_and_settle(State::Stopping)?;
+                    } else if further(front, 1000).is_some() {
+                        self.display_ranges(&frame);
+                        // a move started while still turning curves away from the path
+                        info!("Searching: found a path, stop and look again");
+                        self.stop_and_settle(State::Found)?;
+                    }
+                }
+                Event::Done(completion) if self.is_pending(&completion) => {
+                    info!(
+                        "Searching: rotate {:?}, back to SearchChoice",
+                        completion.status
+                    );
+                    self.state = State::SearchChoice;
+                }
+                _ => {}
+            },
+            State::Moving => match event {
+                Event::Stop => {
+                    info!("Moving received Stop");
+                    self.brain.send(BrainCmd::LidarOnOff(false))?;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                Event::Lidar(state) if state != PowerState::Synced => {
+                    info!("Moving: lidar {state:?}, stop and wait for it");
+                    self.lidar_lost()?;
+                }
+                Event::Frame(frame) => {
+                    let (left, front, right) = ranges(&frame);
+                    // nothing seen ahead isn't a clear path, the return may have been filtered out
+                    if further(front, 500).is_none() || closer(left, 100) || closer(right, 100) {
+                        self.display_ranges(&frame);
+                        info!("Moving: too close, stop");
+                        self.stop_and_settle(State::Stopping)?;
+                    }
+                }
+                Event::Done(completion) if self.is_pending(&completion) => {
+                    info!("Moving: move {:?}, back to SearchChoice", completion.status);
+                    self.state = State::SearchChoice;
+                }
+                _ => {}
+            },
+            State::Stopping => match event {
+                Event::Stop => {
+                    info!("Stopping received Stop");
+                    self.timeout = None;
+                    self.brain.send(BrainCmd::LidarOnOff(false))?;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                Event::Lidar(state) if state != PowerState::Synced => {
+                    info!("Stopping: lidar {state:?}, wait for it");
+                    self.lidar_lost()?;
+                }
+                Event::Timeout => {
+                    info!("Stopping: settled, back to SearchChoice");
+                    self.state = State::SearchChoice;
+                }
+                _ => {}
+            },
+            State::Found => match event {
+                Event::Stop => {
+                    info!("Found received Stop");
+                    self.timeout = None;
+                    self.brain.send(BrainCmd::LidarOnOff(false))?;
+                    self.drive(DriveCmd::Stop)?;
+                    self.state = State::Idle;
+                }
+                Event::Lidar(state) if state != PowerState::Synced => {
+                    info!("Found: lidar {state:?}, wait for it");
+                    self.lidar_lost()?;
+                }
+                // the timeout is cleared once the rover has settled
+                Event::Frame(frame) if self.timeout.is_none() => {
+                    self.display_ranges(&frame);
+                    if let Some(front) = further(ranges(&frame).1, 1000) {
+                        info!("Found: go go go!");
+                        self.drive(DriveCmd::Move(front as i64))?;
+                        self.state = State::Moving;
+                    } else {
+                        info!("Found: turned past the path, back to SearchChoice");
+                        self.state = State::SearchChoice;
+                    }
+                }
+                _ => {}
+            },
+        }
+        Ok(())
+    }
+
+    fn display_ranges(&self, frame: &Frame) {
+        let show = |range: Option<u32>| range.map_or("-".to_string(), |r| r.to_string());
+        let left = show(frame.get_range_left());
+        let front = show(frame.get_range_front());
+        let right = show(frame.get_range_right());
+        info!("l/f/r: {left} / {front} / {right}");
+    }
+
+    pub fn run(&self) -> Result<(), BrainError> {
+        thread::sleep(Duration::from_millis(100));
+        Ok(())
+    }
+}
+
+// (left, front, right) in mm, None for a sector without data
+fn ranges(frame: &Frame) -> (Option<u32>, Option<u32>, Option<u32>) {
+    (
+        frame.get_range_left(),
+        frame.get_range_front(),
+        frame.get_range_right(),
+    )
+}
+
+// a sector without data is neither close nor far, it is not known
+fn closer(range: Option<u32>, limit: u32) -> bool {
+    range.is_some_and(|range| range < limit)
+}
+
+fn further(range: Option<u32>, limit: u32) -> Option<u32> {
+    range.filter(|range| *range > limit)
+}
+
+#[derive(Debug)]
+pub enum BrainError {
+    Drive(SendError<DriveCmd>),
+    IO(std::io::Error),
+    Brain(SendError<BrainCmd>),
+}
+
+impl std::fmt::Display for BrainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrainError::Drive(e) => write!(f, "drive: {e}"),
+            BrainError::IO(e) => write!(f, "io: {e}"),
+            BrainError::Brain(e) => write!(f, "brain: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BrainError {}
+
+impl From<SendError<DriveCmd>> for BrainError {
+    fn from(e: SendError<DriveCmd>) -> Self {
+        BrainError::Drive(e)
+    }
+}
+
+impl From<std::io::Error> for BrainError {
+    fn from(e: std::io::Error) -> Self {
+        BrainError::IO(e)
+    }
+}
+
+impl From<SendError<BrainCmd>> for BrainError {
+    fn from(e: SendError<BrainCmd>) -> Self {
+        BrainError::Brain(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use differential_drive::Notifier;
+    use lidar::Filter;
+    use lidar::ScanPoint;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        sent: Arc<Mutex<Vec<DriveCmd>>>,
+        notifier: Notifier,
+    }
+
+    impl DriveControl for Recorder {
+        fn send(&self, cmd: DriveCmd) -> Result<CommandId, SendError<DriveCmd>> {
+            self.sent.lock().unwrap().push(cmd);
+            Ok(CommandId::next())
+        }
+
+        fn is_active(&self) -> bool {
+            false
+        }
+
+        fn notifier(&self) -> &Notifier {
+            &self.notifier
+        }
+    }
+
+    fn simple(state: State) -> (Simple<Recorder>, Arc<Mutex<Vec<DriveCmd>>>) {
+        let drive = Recorder::default();
+        let sent = drive.sent.clone();
+        let (brain, _) = channel();
+        let (tx, rx) = channel();
+        let simple = Simple {
+            brain,
+            drive,
+            state,
+            timeout: None,
+            pending: None,
+            lidar: PowerState::Synced,
+            tx,
+            rx,
+        };
+        (simple, sent)
+    }
+
+    // walls 1500 mm to the left and 1000 mm to the right, a single far return straight ahead
+    fn frame(filters: &'static [Filter]) -> Frame {
+        let mut data = lidar::Data::new(lidar::Config {
+            filters,
+            ..lidar::Config::DEFAULT
+        });
+        let now = Instant::now();
+        let mut add = |angle: f32, distance: u16| {
+            data.add_point(ScanPoint {
+                angle,
+                distance,
+                intensity: 200,
+                time: now,
+            })
+        };
+        (230..310).for_each(|angle| add(angle as f32, 1500));
+        (50..130).for_each(|angle| add(angle as f32, 1000));
+        add(0.0, 3000);
+        data.get_frame()
+    }
+
+    const ISOLATED: &[Filter] = &[Filter::Isolated {
+        neighbours: 2,
+        max_gap: 100,
+    }];
+
+    #[test]
+    fn goes_for_a_far_front() {
+        let (mut simple, sent) = simple(State::SearchChoice);
+        simple.event(Event::Frame(frame(&[]))).unwrap();
+        assert!(matches!(sent.lock().unwrap()[..], [DriveCmd::Move(3000)]));
+        assert!(matches!(simple.state, State::Moving));
+    }
+
+    #[test]
+    fn front_emptied_by_the_filters_is_not_open() {
+        let frame = frame(ISOLATED);
+        assert_eq!(frame.get_range_front(), None);
+        let (mut simple, sent) = simple(State::SearchChoice);
+        simple.event(Event::Frame(frame)).unwrap();
+        // searches toward the further wall instead
+        assert!(matches!(sent.lock().unwrap()[..], [DriveCmd::Rotate(-270)]));
+        assert!(matches!(simple.state, State::Searching));
+    }
+
+    #[test]
+    fn moving_stops_when_the_front_has_no_data() {
+        let (mut simple, sent) = simple(State::Moving);
+        simple.event(Event::Frame(frame(ISOLATED))).unwrap();
+        assert!(matches!(sent.lock().unwrap()[..], [DriveCmd::Stop]));
+        assert!(matches!(simple.state, State::Stopping));
+    }
+
+    #[test]
+    fn found_path_is_taken_once_settled() {
+        let (mut simple, sent) = simple(State::Searching);
+        simple.event(Event::Frame(frame(&[]))).unwrap();
+        assert!(matches!(simple.state, State::Found));
+        // still turning
+        simple.event(Event::Frame(frame(&[]))).unwrap();
+        assert!(matches!(sent.lock().unwrap()[..], [DriveCmd::Stop]));
+        // as `next_event` does when the settle time is up
+        simple.timeout = None;
+        simple.event(Event::Timeout).unwrap();
+        simple.event(Event::Frame(frame(&[]))).unwrap();
+        assert!(matches!(
+            sent.lock().unwrap()[..],
+            [DriveCmd::Stop, DriveCmd::Move(3000)]
+        ));
+        assert!(matches!(simple.state, State::Moving));
+    }
+}
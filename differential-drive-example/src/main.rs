@@ -18,7 +18,6 @@ use speed_control::SpeedControl;
 use wheel::Wheel;
 
 const WHEEL_DIAMETER: i32 = 60; // 60 mm wheel diameter
-const WHEEL_DISTANCE: i32 = 100; // distance bewtween wheels
 
 const TICKS_PER_REVOLUTION: u32 = 1440;
 
@@ -34,6 +33,15 @@ const POSITION_CONFIG: position_control::Config = position_control::Config {
     p: 0.5,
     i: 0.01,
     d: 0.2,
+    acceleration: 1440.0,
+    jerk: Some(7200.0),
+};
+
+const DRIVE_CONFIG: differential_drive::Config = differential_drive::Config {
+    wheel_distance: 100, // distance bewtween wheels
+    odometry_interval: Duration::from_millis(20),
+    slip: 0.01,
+    velocity_timeout: Duration::from_millis(500),
 };
 
 fn main() -> anyhow::Result<()> {
@@ -107,7 +115,7 @@ fn main() -> anyhow::Result<()> {
 
     let wheel_left = Wheel::new(position_left, WHEEL_DIAMETER);
     let wheel_right = Wheel::new(position_right, WHEEL_DIAMETER);
-    let drive = Drive::new(wheel_left.clone(), wheel_right.clone(), WHEEL_DISTANCE)?;
+    let drive = Drive::new(wheel_left.clone(), wheel_right.clone(), DRIVE_CONFIG)?;
 
     let mut reporter = PositionReporter::new();
 
@@ -0,0 +1,510 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::SendError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use hal::HalError;
+use hal::Priority;
+use log::*;
+
+use wheel::CommandId;
+use wheel::Completion;
+use wheel::Notifier;
+use wheel::Status;
+use wheel::Wheel;
+
+use crate::kinematics;
+use crate::odometry::Odometry;
+use crate::odometry::PoseHistory;
+use crate::Config;
+use crate::Covariance;
+use crate::DriveCmd;
+use crate::Pose;
+
+// long enough to cover a lidar scan and the points it keeps
+const POSE_HISTORY: Duration = Duration::from_secs(1);
+
+enum Event {
+    Command((CommandId, DriveCmd)),
+    Wheel(Completion), // a wheel move finished
+}
+
+// The command in progress and the wheel moves it's waiting for.
+struct Pending {
+    id: CommandId,
+    wheels: Vec<CommandId>,
+    status: Status,
+}
+
+impl Pending {
+    // the status of the command once all its wheel moves are done
+    fn wheel_done(&mut self, completion: Completion) -> Option<Status> {
+        let index = self.wheels.iter().position(|id| *id == completion.id)?;
+        self.wheels.remove(index);
+        match (self.status, completion.status) {
+            (_, Status::Failed) => self.status = Status::Failed,
+            (Status::Completed, Status::Cancelled) => self.status = Status::Cancelled,
+            _ => {}
+        }
+        self.wheels.is_empty().then_some(self.status)
+    }
+
+    fn move_wheel(&mut self, wheel: &Wheel, position: i64) {
+        match wheel.set_position(position) {
+            Ok(id) => self.wheels.push(id),
+            Err(err) => {
+                error!("{}: failed to move wheel: {err}", self.id);
+                self.status = Status::Failed;
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct Drive<'d> {
+    tx: Sender<Event>,
+    left_wheel: Wheel<'d>,
+    right_wheel: Wheel<'d>,
+    odometry: Arc<Mutex<Odometry>>,
+    history: Arc<Mutex<PoseHistory>>,
+    velocity: Arc<AtomicBool>,
+    notifier: Notifier,
+}
+
+#[allow(dead_code)]
+impl<'d> Drive<'d> {
+    pub fn new(
+        left_wheel: Wheel<'static>,
+        right_wheel: Wheel<'static>,
+        config: Config,
+    ) -> Result<Self, DriveError> {
+        let wheel_dist = config.wheel_distance;
+        let (tx, rx): (Sender<Event>, Receiver<Event>) = channel();
+        let odometry = Arc::new(Mutex::new(Odometry::new(wheel_dist as f64, config.slip)));
+        let history = Arc::new(Mutex::new(PoseHistory::new(POSE_HISTORY)));
+        {
+            let left_wheel = left_wheel.clone();
+            let right_wheel = right_wheel.clone();
+            let odometry = odometry.clone();
+            let history = history.clone();
+            let (tick_tx, tick_rx) = channel();
+            let timer = hal::timer(move || tick_tx.send(()).unwrap())?;
+            hal::spawn("odometry", 4096, Priority::Medium, move || {
+                timer.every(config.odometry_interval).unwrap();
+                loop {
+                    tick_rx.recv().unwrap();
+                    let left = left_wheel.get_distance();
+                    let right = right_wheel.get_distance();
+                    let mut odometry = odometry.lock().unwrap();
+                    odometry.update(left, right);
+                    history
+                        .lock()
+                        .unwrap()
+                        .push(Instant::now(), odometry.pose());
+                }
+            })?;
+        }
+        let velocity = Arc::new(AtomicBool::new(false));
+        let notifier = Notifier::new();
+        for wheel in [&left_wheel, &right_wheel] {
+            let tx = tx.clone();
+            wheel.notifier().on_complete(move |completion| {
+                // the drive thread only goes away with the rover
+                let _ = tx.send(Event::Wheel(completion));
+            });
+        }
+        {
+            let left_wheel = left_wheel.clone();
+            let right_wheel = right_wheel.clone();
+            let velocity = velocity.clone();
+            let notifier = notifier.clone();
+            hal::spawn("drive", 4096, Priority::Medium, move || {
+                // deadline for the next Velocity command while in velocity mode
+                let mut watchdog: Option<Instant> = None;
+                let mut current: Option<Pending> = None;
+                loop {
+                    let event = match watchdog {
+                        Some(deadline) => {
+                            let timeout = deadline.saturating_duration_since(Instant::now());
+                            match rx.recv_timeout(timeout) {
+                                Ok(event) => event,
+                                Err(RecvTimeoutError::Timeout) => {
+                                    warn!(
+                                        "no velocity command for {:?}, stopping",
+                                        config.velocity_timeout
+                                    );
+                                    left_wheel.set_speed(0.0).unwrap();
+                                    right_wheel.set_speed(0.0).unwrap();
+                                    velocity.store(false, Ordering::Relaxed);
+                                    watchdog = None;
+                                    if let Some(pending) = current.take() {
+                                        notifier.notify(pending.id, Status::Cancelled);
+                                    }
+                                    continue;
+                                }
+                                Err(RecvTimeoutError::Disconnected) => {
+                                    panic!("drive channel closed")
+                                }
+                            }
+                        }
+                        None => rx.recv().unwrap(),
+                    };
+                    let (id, cmd) = match event {
+                        Event::Command(command) => command,
+                        Event::Wheel(completion) => {
+                            if let Some(pending) = current.as_mut() {
+                                if let Some(status) = pending.wheel_done(completion) {
+                                    notifier.notify(pending.id, status);
+                                    current = None;
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    // a velocity command is done when the next one replaces it, anything else
+                    // still in progress is cancelled
+                    if let Some(previous) = current.take() {
+                        let status = match (watchdog.is_some(), cmd) {
+                            (true, DriveCmd::Velocity { .. }) => Status::Completed,
+                            _ => Status::Cancelled,
+                        };
+                        notifier.notify(previous.id, status);
+                    }
+                    let mut pending = Pending {
+                        id,
+                        wheels: Vec::new(),
+                        status: Status::Completed,
+                    };
+                    // leaving velocity mode, the position commands (and Stop) leave the speed alone
+                    if watchdog.is_some() && !matches!(cmd, DriveCmd::Velocity { .. }) {
+                        left_wheel.set_speed(0.0).unwrap();
+                        right_wheel.set_speed(0.0).unwrap();
+                        velocity.store(false, Ordering::Relaxed);
+                        watchdog = None;
+                    }
+                    // only Drive moves at a reduced speed
+                    if !matches!(cmd, DriveCmd::Drive(_) | DriveCmd::Stop) {
+                        left_wheel.set_max_speed(None).unwrap();
+                        right_wheel.set_max_speed(None).unwrap();
+                    }
+                    match cmd {
+                        DriveCmd::Move(distance) => {
+                            info!("Move {distance}");
+                            let pos_left = left_wheel.get_position() + distance;
+                            let pos_right = right_wheel.get_position() + distance;
+                            pending.move_wheel(&left_wheel, pos_left);
+                            pending.move_wheel(&right_wheel, pos_right);
+                        }
+                        DriveCmd::Rotate(degrees) => {
+                            info!("Rotate {degrees}");
+                            let (left, right) = kinematics::spin(degrees as f64, wheel_dist as f64);
+                            let pos_left = left_wheel.get_position() + left as i64;
+                            let pos_right = right_wheel.get_position() + right as i64;
+                            pending.move_wheel(&left_wheel, pos_left);
+                            pending.move_wheel(&right_wheel, pos_right);
+                        }
+                        DriveCmd::Pivot(degrees) => {
+                            info!("Pivot {degrees}");
+                            // turn by using only one wheel (yes - it throws us out of position slightly)
+                            let (left, right) =
+                                kinematics::pivot(degrees as f64, wheel_dist as f64);
+                            if degrees > 0 {
+                                let pos_left = left_wheel.get_position() + left as i64;
+                                pending.move_wheel(&left_wheel, pos_left);
+                            } else {
+                                let pos_right = right_wheel.get_position() + right as i64;
+                                pending.move_wheel(&right_wheel, pos_right);
+                            }
+                        }
+                        DriveCmd::Drive((translational, angular, distance)) => {
+                            info!("Drive {translational} {angular} {distance}");
+                            let (left, right) = kinematics::arc(
+                                translational as f64,
+                                angular as f64,
+                                distance as f64,
+                                wheel_dist as f64,
+                                left_wheel.max_speed().min(right_wheel.max_speed()),
+                            );
+                            info!("left={left:?} right={right:?}");
+                            // a wheel that doesn't need to move would never reach a zero speed target
+                            if left.distance.abs() >= 1.0 {
+                                let pos_left = left_wheel.get_position() + left.distance as i64;
+                                left_wheel.set_max_speed(Some(left.speed)).unwrap();
+                                pending.move_wheel(&left_wheel, pos_left);
+                            }
+                            if right.distance.abs() >= 1.0 {
+                                let pos_right = right_wheel.get_position() + right.distance as i64;
+                                right_wheel.set_max_speed(Some(right.speed)).unwrap();
+                                pending.move_wheel(&right_wheel, pos_right);
+                            }
+                        }
+                        DriveCmd::Left(distance) => {
+                            info!("LeftWheel {distance}");
+                            let pos_left = left_wheel.get_position() + distance;
+                            pending.move_wheel(&left_wheel, pos_left);
+                        }
+                        DriveCmd::Right(distance) => {
+                            info!("RightWheel {distance}");
+                            let pos_right = right_wheel.get_position() + distance;
+                            pending.move_wheel(&right_wheel, pos_right);
+                        }
+                        DriveCmd::Velocity {
+                            linear_mm_s,
+                            angular_deg_s,
+                        } => {
+                            debug!("Velocity {linear_mm_s} {angular_deg_s}");
+                            let (left, right) = kinematics::wheel_speeds(
+                                linear_mm_s as f64,
+                                angular_deg_s as f64,
+                                wheel_dist as f64,
+                            );
+                            left_wheel.set_speed(left).unwrap();
+                            right_wheel.set_speed(right).unwrap();
+                            velocity.store(true, Ordering::Relaxed);
+                            watchdog = Some(Instant::now() + config.velocity_timeout);
+                        }
+                        DriveCmd::Stop => {
+                            left_wheel.stop().unwrap();
+                            right_wheel.stop().unwrap();
+                        }
+                    }
+                    if pending.wheels.is_empty() && !matches!(cmd, DriveCmd::Velocity { .. }) {
+                        notifier.notify(pending.id, pending.status);
+                    } else {
+                        current = Some(pending);
+                    }
+                }
+            })?;
+        }
+        Ok(Drive {
+            tx,
+            left_wheel,
+            right_wheel,
+            odometry,
+            history,
+            velocity,
+            notifier,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.velocity.load(Ordering::Relaxed)
+            || self.left_wheel.is_active()
+            || self.right_wheel.is_active()
+    }
+
+    pub fn send(&self, cmd: DriveCmd) -> Result<CommandId, SendError<DriveCmd>> {
+        let id = CommandId::next();
+        self.tx
+            .send(Event::Command((id, cmd)))
+            .map_err(|_| SendError(cmd))?;
+        Ok(id)
+    }
+
+    // completions of the commands sent with `send`
+    pub fn notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    pub fn pose(&self) -> (Pose, Covariance) {
+        let odometry = self.odometry.lock().unwrap();
+        (odometry.pose(), odometry.covariance())
+    }
+
+    // where the rover was at `time`, interpolated between the odometry updates, None when it's
+    // older than the history kept
+    pub fn pose_at(&self, time: Instant) -> Option<Pose> {
+        self.history.lock().unwrap().at(time)
+    }
+
+    // for looking up many times with one lock
+    pub fn pose_history(&self) -> PoseHistory {
+        self.history.lock().unwrap().clone()
+    }
+
+    pub fn reset_pose(&self, pose: Pose) {
+        info!("reset pose: {pose:?}");
+        let mut odometry = self.odometry.lock().unwrap();
+        odometry.reset(pose);
+        // the earlier poses are in the old frame
+        self.history.lock().unwrap().clear();
+    }
+}
+
+#[derive(Debug)]
+pub enum DriveError {
+    HalError(HalError),
+    IOError(std::io::Error),
+}
+
+impl std::fmt::Display for DriveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for DriveError {}
+
+impl From<HalError> for DriveError {
+    fn from(e: HalError) -> Self {
+        DriveError::HalError(e)
+    }
+}
+
+impl From<std::io::Error> for DriveError {
+    fn from(e: std::io::Error) -> Self {
+        DriveError::IOError(e)
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use std::thread;
+
+    use encoder::Encoder;
+    use hal::host::SimCounter;
+    use hal::host::SimPin;
+    use hal::host::SimPwm;
+    use motor::Motor;
+    use position_control::PositionControl;
+    use speed_control::SpeedControl;
+
+    use super::*;
+
+    const TICKS_PER_REVOLUTION: u32 = 1440;
+    const FULL_SPEED: f32 = 1000.0; // degrees per second at 100%
+    const VELOCITY_TIMEOUT: Duration = Duration::from_millis(200);
+
+    const CONFIG: Config = Config {
+        wheel_distance: 100,
+        odometry_interval: Duration::from_millis(20),
+        slip: 0.01,
+        velocity_timeout: VELOCITY_TIMEOUT,
+    };
+
+    // A wheel whose motor speed follows the duty and turns the encoder, and its PWM.
+    fn wheel(name: &str) -> (Wheel<'static>, SimPwm) {
+        let phase = SimPin::new();
+        let pwm = SimPwm::new(1000);
+        let counter = SimCounter::new();
+        let motor = Motor::with_driver(name, phase.clone(), pwm.clone(), false);
+        let encoder = Encoder::with_counter(counter.clone(), TICKS_PER_REVOLUTION, false);
+        {
+            let pwm = pwm.clone();
+            thread::spawn(move || {
+                let ticks_per_degree = TICKS_PER_REVOLUTION as f32 / 360.0;
+                let mut last = Instant::now();
+                let mut ticks = 0.0f32;
+                loop {
+                    thread::sleep(Duration::from_millis(1));
+                    let now = Instant::now();
+                    let direction = if phase.is_high() { -1.0 } else { 1.0 };
+                    let speed = direction * pwm.get_percent() / 100.0 * FULL_SPEED;
+                    let before = ticks as i64;
+                    ticks += speed * ticks_per_degree * (now - last).as_secs_f32();
+                    counter.add(ticks as i64 - before);
+                    last = now;
+                }
+            });
+        }
+        let speed = SpeedControl::new(
+            motor,
+            encoder,
+            speed_control::Config {
+                interval: Duration::from_millis(10),
+                p: 2.0,
+                i: 1.0,
+                d: 0.0,
+            },
+        )
+        .unwrap();
+        let position = PositionControl::new(
+            speed,
+            position_control::Config {
+                interval: Duration::from_millis(10),
+                p: 0.5,
+                i: 0.01,
+                d: 0.2,
+                acceleration: 1440.0,
+                jerk: None,
+            },
+            720.0,
+        )
+        .unwrap();
+        (Wheel::new(position, 60), pwm)
+    }
+
+    fn drive() -> (Drive<'static>, SimPwm, SimPwm) {
+        let (left, left_pwm) = wheel("left");
+        let (right, right_pwm) = wheel("right");
+        (
+            Drive::new(left, right, CONFIG).unwrap(),
+            left_pwm,
+            right_pwm,
+        )
+    }
+
+    const FORWARD: DriveCmd = DriveCmd::Velocity {
+        linear_mm_s: 100.0,
+        angular_deg_s: 0.0,
+    };
+
+    #[test]
+    fn velocity_stops_without_a_new_command() {
+        let (drive, left, right) = drive();
+        let completions = drive.notifier().subscribe();
+        let sent = Instant::now();
+        let id = drive.send(FORWARD).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(drive.is_active());
+        assert!(left.get_duty() > 0 && right.get_duty() > 0);
+
+        let completion = completions.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            completion,
+            Completion {
+                id,
+                status: Status::Cancelled
+            }
+        );
+        assert!(sent.elapsed() >= VELOCITY_TIMEOUT);
+        thread::sleep(Duration::from_millis(50));
+        assert!(!drive.is_active());
+        assert_eq!((left.get_duty(), right.get_duty()), (0, 0));
+    }
+
+    #[test]
+    fn velocity_is_completed_by_the_next() {
+        let (drive, _, _) = drive();
+        let completions = drive.notifier().subscribe();
+        let first = drive.send(FORWARD).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let second = drive.send(FORWARD).unwrap();
+        assert_eq!(
+            completions.recv_timeout(Duration::from_secs(1)).unwrap(),
+            Completion {
+                id: first,
+                status: Status::Completed
+            }
+        );
+        // and the watchdog restarted, the second runs its own timeout
+        assert!(completions
+            .recv_timeout(Duration::from_millis(150))
+            .is_err());
+        assert_eq!(
+            completions.recv_timeout(Duration::from_secs(1)).unwrap(),
+            Completion {
+                id: second,
+                status: Status::Cancelled
+            }
+        );
+    }
+}
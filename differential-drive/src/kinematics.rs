@@ -0,0 +1,138 @@
+// Distance (mm) and speed (mm/s) for one wheel of a move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelMove {
+    pub distance: f64,
+    pub speed: f64,
+}
+
+// Wheel speeds (left, right) in mm/s for `translational` mm/s and `angular` degrees/s (counter
+// clockwise is positive).
+pub fn wheel_speeds(translational: f64, angular: f64, wheel_dist: f64) -> (f64, f64) {
+    let speed_diff = angular.to_radians() * (wheel_dist / 2.0);
+    (translational - speed_diff, translational + speed_diff)
+}
+
+// Wheel moves for driving an arc at `translational` mm/s and `angular` degrees/s (counter clockwise
+// is positive) until the centre of the rover has travelled `distance` mm, both wheels take the same
+// time. With no translational velocity the rover turns in place and `distance` is the travel of
+// each wheel. When the faster wheel would go over `max_speed` mm/s both wheels slow down by the
+// same factor, the arc is the same but takes longer. Returns (left, right).
+pub fn arc(
+    translational: f64,
+    angular: f64,
+    distance: f64,
+    wheel_dist: f64,
+    max_speed: f64,
+) -> (WheelMove, WheelMove) {
+    let (speed_left, speed_right) = wheel_speeds(translational, angular, wheel_dist);
+    let speed_diff = (speed_right - speed_left) / 2.0;
+    let duration = if translational != 0.0 {
+        (distance / translational).abs()
+    } else if speed_diff != 0.0 {
+        (distance / speed_diff).abs()
+    } else {
+        0.0
+    };
+    let fastest = speed_left.abs().max(speed_right.abs());
+    let slow_down = match fastest > max_speed {
+        true => max_speed / fastest,
+        false => 1.0,
+    };
+    let (speed_left, speed_right) = (speed_left * slow_down, speed_right * slow_down);
+    let duration = duration / slow_down;
+    (
+        WheelMove {
+            distance: speed_left * duration,
+            speed: speed_left.abs(),
+        },
+        WheelMove {
+            distance: speed_right * duration,
+            speed: speed_right.abs(),
+        },
+    )
+}
+
+// Wheel distances (left, right) in mm to spin `degrees` (clockwise is positive) around the centre
+// of the rover, each wheel travels half the arc in opposite directions.
+pub fn spin(degrees: f64, wheel_dist: f64) -> (f64, f64) {
+    let distance = degrees.to_radians() * (wheel_dist / 2.0);
+    (distance, -distance)
+}
+
+// Wheel distances (left, right) in mm to pivot around the stationary inside wheel, clockwise is
+// positive. `degrees` is the original single wheel Rotate: the outside wheel travels as far as it
+// would spinning `degrees` in place, which turns the rover by `degrees / 2`.
+pub fn pivot(degrees: f64, wheel_dist: f64) -> (f64, f64) {
+    let distance = degrees.to_radians() * (wheel_dist / 2.0);
+    if degrees > 0.0 {
+        (distance, 0.0)
+    } else {
+        (0.0, distance.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHEEL_DIST: f64 = 100.0;
+
+    #[test]
+    fn spin_moves_the_wheels_apart_by_half_the_arc() {
+        let (left, right) = spin(90.0, WHEEL_DIST);
+        let half = std::f64::consts::PI / 2.0 * WHEEL_DIST / 2.0;
+        assert!((left - half).abs() < 1e-9);
+        assert!((right + half).abs() < 1e-9);
+    }
+
+    // the travel the single wheel Rotate had before spin was added
+    #[test]
+    fn pivot_moves_the_outside_wheel_as_before() {
+        let degrees_per_mm = 360.0 / (WHEEL_DIST * std::f64::consts::PI);
+        let (left, right) = pivot(90.0, WHEEL_DIST);
+        assert!((left - 90.0 / degrees_per_mm).abs() < 1e-9);
+        assert_eq!(right, 0.0);
+        let (left, right) = pivot(-90.0, WHEEL_DIST);
+        assert_eq!(left, 0.0);
+        assert!((right - 90.0 / degrees_per_mm).abs() < 1e-9);
+    }
+
+    // turning about the inside wheel the heading changes by the outside wheel travel over the
+    // wheel distance
+    #[test]
+    fn pivot_turns_half_the_degrees() {
+        let (left, _) = pivot(90.0, WHEEL_DIST);
+        assert!((left / WHEEL_DIST - 45f64.to_radians()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn arc_wheels_finish_together() {
+        let (left, right) = arc(200.0, 45.0, 1000.0, WHEEL_DIST, 1000.0);
+        let left_time = left.distance / left.speed;
+        let right_time = right.distance / right.speed;
+        assert!((left_time - right_time).abs() < 1e-9);
+        assert!(((left.distance + right.distance) / 2.0 - 1000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn arc_over_the_max_speed_slows_both_wheels() {
+        // the outside wheel would need 200 + 90 degrees/s * 50mm = 278.5 mm/s
+        let (left, right) = arc(200.0, 90.0, 1000.0, WHEEL_DIST, 250.0);
+        let (fast_left, fast_right) = arc(200.0, 90.0, 1000.0, WHEEL_DIST, 1000.0);
+        assert!((right.speed - 250.0).abs() < 1e-9);
+        assert!((left.speed / right.speed - fast_left.speed / fast_right.speed).abs() < 1e-9);
+        // the same arc
+        assert!((left.distance - fast_left.distance).abs() < 1e-9);
+        assert!((right.distance - fast_right.distance).abs() < 1e-9);
+        assert!((left.distance / left.speed - right.distance / right.speed).abs() < 1e-9);
+    }
+
+    #[test]
+    fn spin_arc_over_the_max_speed() {
+        let (left, right) = arc(0.0, 720.0, 100.0, WHEEL_DIST, 500.0);
+        assert!((left.speed - 500.0).abs() < 1e-9);
+        assert!((right.speed - 500.0).abs() < 1e-9);
+        assert!((left.distance + 100.0).abs() < 1e-9);
+        assert!((right.distance - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+// Pose in mm, heading in radians counter clockwise from the x axis, the rover starts at the origin
+// facing along x.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+}
+
+// Covariance of (x, y, heading).
+pub type Covariance = [[f64; 3]; 3];
+
+// Dead reckoning from the wheel travel, the covariance grows with the distance each wheel travels
+// (variance of `slip` mm^2 per mm).
+#[derive(Debug, Clone)]
+pub struct Odometry {
+    wheel_distance: f64,
+    slip: f64,
+    pose: Pose,
+    covariance: Covariance,
+    last: Option<(f64, f64)>,
+}
+
+impl Odometry {
+    pub fn new(wheel_distance: f64, slip: f64) -> Self {
+        Self {
+            wheel_distance,
+            slip,
+            pose: Pose::default(),
+            covariance: [[0.0; 3]; 3],
+            last: None,
+        }
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    pub fn covariance(&self) -> Covariance {
+        self.covariance
+    }
+
+    // the next update continues from the current wheel positions
+    pub fn reset(&mut self, pose: Pose) {
+        self.pose = pose;
+        self.covariance = [[0.0; 3]; 3];
+    }
+
+    // `left` and `right` are the absolute wheel positions in mm
+    pub fn update(&mut self, left: f64, right: f64) {
+        let (last_left, last_right) = self.last.replace((left, right)).unwrap_or((left, right));
+        let dl = left - last_left;
+        let dr = right - last_right;
+        if dl == 0.0 && dr == 0.0 {
+            return;
+        }
+        let b = self.wheel_distance;
+        let ds = (dr + dl) / 2.0;
+        let dtheta = (dr - dl) / b;
+        // integrate along the chord at the mean heading
+        let a = self.pose.heading + dtheta / 2.0;
+        let (sin, cos) = a.sin_cos();
+        self.pose.x += ds * cos;
+        self.pose.y += ds * sin;
+        self.pose.heading = normalize(self.pose.heading + dtheta);
+
+        // P = Fp P Fp' + Fw Q Fw'
+        let fp = [[1.0, 0.0, -ds * sin], [0.0, 1.0, ds * cos], [0.0, 0.0, 1.0]];
+        let k = ds / (2.0 * b);
+        let fw = [
+            [0.5 * cos - k * sin, 0.5 * cos + k * sin],
+            [0.5 * sin + k * cos, 0.5 * sin - k * cos],
+            [1.0 / b, -1.0 / b],
+        ];
+        let q = [self.slip * dr.abs(), self.slip * dl.abs()];
+        let p = self.covariance;
+        let mut next = [[0.0; 3]; 3];
+        for (i, row) in next.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                for m in 0..3 {
+                    for n in 0..3 {
+                        *value += fp[i][m] * p[m][n] * fp[j][n];
+                    }
+                }
+                for (m, q) in q.iter().enumerate() {
+                    *value += fw[i][m] * q * fw[j][m];
+                }
+            }
+        }
+        self.covariance = next;
+    }
+}
+
+// Recent poses by time, so a sensor reading can be matched with where the rover was when it was
+// taken. Poses older than `length` are dropped.
+#[derive(Debug, Clone)]
+pub struct PoseHistory {
+    length: Duration,
+    poses: VecDeque<(Instant, Pose)>,
+}
+
+impl PoseHistory {
+    pub fn new(length: Duration) -> Self {
+        Self {
+            length,
+            poses: VecDeque::new(),
+        }
+    }
+
+    // `time` is after the last pose added
+    pub fn push(&mut self, time: Instant, pose: Pose) {
+        while self
+            .poses
+            .front()
+            .is_some_and(|(t, _)| time.saturating_duration_since(*t) > self.length)
+        {
+            self.poses.pop_front();
+        }
+        self.poses.push_back((time, pose));
+    }
+
+    pub fn clear(&mut self) {
+        self.poses.clear();
+    }
+
+    // interpolated between the poses around `time`, the latest pose after it, None when `time` is
+    // before the history
+    pub fn at(&self, time: Instant) -> Option<Pose> {
+        let next = self.poses.partition_point(|(t, _)| *t < time);
+        if next == self.poses.len() {
+            return self.poses.back().map(|(_, pose)| *pose);
+        }
+        let (t1, p1) = self.poses[next];
+        if next == 0 {
+            return (t1 == time).then_some(p1);
+        }
+        let (t0, p0) = self.poses[next - 1];
+        let f = (time - t0).as_secs_f64() / (t1 - t0).as_secs_f64();
+        Some(Pose {
+            x: p0.x + (p1.x - p0.x) * f,
+            y: p0.y + (p1.y - p0.y) * f,
+            heading: normalize(p0.heading + normalize(p1.heading - p0.heading) * f),
+        })
+    }
+}
+
+// wrap to -PI..=PI
+pub fn normalize(angle: f64) -> f64 {
+    angle.sin().atan2(angle.cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    const WHEEL_DISTANCE: f64 = 100.0;
+    const SLIP: f64 = 0.01;
+
+    // feeds `steps` updates with the wheels moving `left` and `right` mm each
+    fn drive(odometry: &mut Odometry, steps: usize, left: f64, right: f64) {
+        let (mut l, mut r) = odometry.last.unwrap_or((0.0, 0.0));
+        odometry.update(l, r);
+        for _ in 0..steps {
+            l += left;
+            r += right;
+            odometry.update(l, r);
+        }
+    }
+
+    fn trace(covariance: &Covariance) -> f64 {
+        (0..3).map(|i| covariance[i][i]).sum()
+    }
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn straight() {
+        let mut odometry = Odometry::new(WHEEL_DISTANCE, SLIP);
+        drive(&mut odometry, 100, 10.0, 10.0);
+        let pose = odometry.pose();
+        assert_near(pose.x, 1000.0, 1e-9);
+        assert_near(pose.y, 0.0, 1e-9);
+        assert_near(pose.heading, 0.0, 1e-9);
+        // driving straight the uncertainty is across the path and in the heading
+        let covariance = odometry.covariance();
+        assert!(covariance[1][1] > covariance[0][0]);
+        assert!(covariance[2][2] > 0.0);
+    }
+
+    #[test]
+    fn spin() {
+        let mut odometry = Odometry::new(WHEEL_DISTANCE, SLIP);
+        // a quarter turn counter clockwise, each wheel travels a quarter of the circle it's on
+        let quarter = PI / 2.0 * WHEEL_DISTANCE / 2.0;
+        drive(&mut odometry, 50, -quarter / 50.0, quarter / 50.0);
+        let pose = odometry.pose();
+        assert_near(pose.x, 0.0, 1e-9);
+        assert_near(pose.y, 0.0, 1e-9);
+        assert_near(pose.heading, PI / 2.0, 1e-9);
+        assert!(odometry.covariance()[2][2] > 0.0);
+    }
+
+    #[test]
+    fn arc() {
+        let mut odometry = Odometry::new(WHEEL_DISTANCE, SLIP);
+        // a half circle of 200mm radius counter clockwise
+        let radius = 200.0;
+        let left = PI * (radius - WHEEL_DISTANCE / 2.0);
+        let right = PI * (radius + WHEEL_DISTANCE / 2.0);
+        drive(&mut odometry, 1000, left / 1000.0, right / 1000.0);
+        let pose = odometry.pose();
+        assert_near(pose.x, 0.0, 0.1);
+        assert_near(pose.y, 2.0 * radius, 0.1);
+        assert_near(pose.heading.abs(), PI, 1e-9);
+    }
+
+    #[test]
+    fn covariance_grows_with_the_distance() {
+        let mut odometry = Odometry::new(WHEEL_DISTANCE, SLIP);
+        let mut last = 0.0;
+        for _ in 0..10 {
+            drive(&mut odometry, 10, 10.0, 12.0);
+            let trace = trace(&odometry.covariance());
+            assert!(trace > last);
+            last = trace;
+        }
+        // standing still adds nothing
+        drive(&mut odometry, 10, 0.0, 0.0);
+        assert_eq!(trace(&odometry.covariance()), last);
+    }
+
+    #[test]
+    fn reset_keeps_the_wheel_positions() {
+        let mut odometry = Odometry::new(WHEEL_DISTANCE, SLIP);
+        drive(&mut odometry, 10, 10.0, 10.0);
+        let start = Pose {
+            x: 100.0,
+            y: 200.0,
+            heading: PI / 2.0,
+        };
+        odometry.reset(start);
+        assert_eq!(trace(&odometry.covariance()), 0.0);
+        // carries on from where the wheels are, along the new heading
+        drive(&mut odometry, 10, 10.0, 10.0);
+        let pose = odometry.pose();
+        assert_near(pose.x, 100.0, 1e-9);
+        assert_near(pose.y, 300.0, 1e-9);
+    }
+
+    fn history(start: Instant) -> PoseHistory {
+        let mut history = PoseHistory::new(Duration::from_secs(1));
+        for i in 0..=10 {
+            let pose = Pose {
+                x: i as f64 * 10.0,
+                y: -(i as f64),
+                heading: 0.0,
+            };
+            history.push(start + Duration::from_millis(i * 20), pose);
+        }
+        history
+    }
+
+    #[test]
+    fn history_interpolates() {
+        let start = Instant::now();
+        let history = history(start);
+        let pose = history.at(start + Duration::from_millis(35)).unwrap();
+        assert_near(pose.x, 17.5, 1e-9);
+        assert_near(pose.y, -1.75, 1e-9);
+        let pose = history.at(start + Duration::from_millis(40)).unwrap();
+        assert_near(pose.x, 20.0, 1e-9);
+    }
+
+    #[test]
+    fn history_ends() {
+        let start = Instant::now();
+        let history = history(start);
+        assert!(history.at(start - Duration::from_millis(1)).is_none());
+        assert_eq!(history.at(start).unwrap().x, 0.0);
+        // the latest pose until there's a newer one
+        assert_eq!(history.at(start + Duration::from_secs(5)).unwrap().x, 100.0);
+        assert!(PoseHistory::new(Duration::from_secs(1)).at(start).is_none());
+    }
+
+    #[test]
+    fn history_heading_takes_the_short_way() {
+        let start = Instant::now();
+        let mut history = PoseHistory::new(Duration::from_secs(1));
+        let pose = |heading| Pose {
+            x: 0.0,
+            y: 0.0,
+            heading,
+        };
+        history.push(start, pose(PI - 0.1));
+        history.push(start + Duration::from_millis(20), pose(-PI + 0.1));
+        let heading = history
+            .at(start + Duration::from_millis(10))
+            .unwrap()
+            .heading;
+        assert_near(heading.abs(), PI, 1e-9);
+    }
+
+    #[test]
+    fn history_drops_old_poses() {
+        let start = Instant::now();
+        let mut history = PoseHistory::new(Duration::from_millis(100));
+        for i in 0..20 {
+            history.push(start + Duration::from_millis(i * 20), Pose::default());
+        }
+        assert!(history.at(start + Duration::from_millis(200)).is_none());
+        assert!(history.at(start + Duration::from_millis(280)).is_some());
+    }
+}
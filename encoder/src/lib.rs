@@ -1,23 +1,26 @@
-use std::cmp::min;
-use std::sync::atomic::AtomicI64;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use esp_idf_hal::gpio::AnyInputPin;
+#[cfg(feature = "esp")]
 use esp_idf_hal::gpio::InputPin;
+#[cfg(feature = "esp")]
 use esp_idf_hal::pcnt::*;
+#[cfg(feature = "esp")]
 use esp_idf_hal::peripheral::Peripheral;
+#[cfg(feature = "esp")]
 use esp_idf_sys::EspError;
+#[cfg(feature = "esp")]
+use hal::esp::PcntCounter;
+use hal::QuadratureCounter;
 
 #[derive(Clone)]
 pub struct Encoder<'d> {
-    pcnt: Arc<PcntDriver<'d>>,
-    position: Arc<AtomicI64>,
+    counter: Arc<dyn QuadratureCounter + 'd>,
     ticks_per_degree: f64,
     invert: bool,
 }
 
 impl<'d> Encoder<'d> {
+    #[cfg(feature = "esp")]
     pub fn new<'a, PCNT: Pcnt>(
         pcnt: impl Peripheral<P = PCNT> + 'd,
         a_pin: impl Peripheral<P = impl InputPin> + 'd,
@@ -25,74 +28,24 @@ impl<'d> Encoder<'d> {
         ticks_per_revolution: u32,
         invert: bool,
     ) -> Result<Self, EspError> {
-        let mut pcnt = PcntDriver::new(
-            pcnt,
-            Some(a_pin),
-            Some(b_pin),
-            Option::<AnyInputPin>::None,
-            Option::<AnyInputPin>::None,
-        )?;
-        const POS_LIMIT: i16 = 100;
-        const NEG_LIMIT: i16 = -100;
-        pcnt.channel_config(
-            PcntChannel::Channel0,
-            PinIndex::Pin0,
-            PinIndex::Pin1,
-            &PcntChannelConfig {
-                lctrl_mode: PcntControlMode::Reverse,
-                hctrl_mode: PcntControlMode::Keep,
-                pos_mode: PcntCountMode::Decrement,
-                neg_mode: PcntCountMode::Increment,
-                counter_h_lim: POS_LIMIT,
-                counter_l_lim: NEG_LIMIT,
-            },
-        )?;
-        pcnt.channel_config(
-            PcntChannel::Channel1,
-            PinIndex::Pin1,
-            PinIndex::Pin0,
-            &PcntChannelConfig {
-                lctrl_mode: PcntControlMode::Reverse,
-                hctrl_mode: PcntControlMode::Keep,
-                pos_mode: PcntCountMode::Increment,
-                neg_mode: PcntCountMode::Decrement,
-                counter_h_lim: POS_LIMIT,
-                counter_l_lim: NEG_LIMIT,
-            },
-        )?;
-        pcnt.set_filter_value(min(10 * 80, 1023))?;
-        pcnt.filter_enable()?;
-        pcnt.event_enable(PcntEvent::HighLimit)?;
-        pcnt.event_enable(PcntEvent::LowLimit)?;
+        let counter = PcntCounter::new(pcnt, a_pin, b_pin)?;
+        Ok(Self::with_counter(counter, ticks_per_revolution, invert))
+    }
 
-        let enc = Encoder {
-            pcnt: Arc::new(pcnt),
-            position: Arc::new(AtomicI64::new(0)),
+    pub fn with_counter(
+        counter: impl QuadratureCounter + 'd,
+        ticks_per_revolution: u32,
+        invert: bool,
+    ) -> Self {
+        Encoder {
+            counter: Arc::new(counter),
             ticks_per_degree: ticks_per_revolution as f64 / 360.0,
             invert,
-        };
-
-        unsafe {
-            let position = enc.position.clone();
-            enc.pcnt.subscribe(move |status| {
-                let status = PcntEventType::from_repr_truncated(status);
-                if status.contains(PcntEvent::HighLimit) {
-                    position.fetch_add(POS_LIMIT as i64, Ordering::Relaxed);
-                }
-                if status.contains(PcntEvent::LowLimit) {
-                    position.fetch_add(NEG_LIMIT as i64, Ordering::Relaxed);
-                }
-            })?;
         }
-        enc.pcnt.counter_pause()?;
-        enc.pcnt.counter_clear()?;
-        enc.pcnt.counter_resume()?;
-        Ok(enc)
     }
 
     pub fn get_raw_position(&self) -> i64 {
-        let mut raw =
-            self.position.load(Ordering::Relaxed) + self.pcnt.get_counter_value().unwrap() as i64;
+        let mut raw = self.counter.get_count();
         if self.invert {
             raw = -raw;
         }
@@ -110,12 +63,57 @@ impl<'d> Encoder<'d> {
             true => -position,
             false => position,
         };
-        // small race condition here but clearing the counter first means that we have *_LIMIT pcnt counts before we'd
-        // update the position
-        self.pcnt.counter_clear().unwrap();
-        self.position.store(
-            (raw as f64 * self.ticks_per_degree) as i64,
-            Ordering::Relaxed,
-        );
+        self.counter
+            .set_count((raw as f64 * self.ticks_per_degree) as i64);
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use hal::host::SimCounter;
+
+    use super::*;
+
+    // 4 ticks a degree
+    const TICKS_PER_REVOLUTION: u32 = 1440;
+
+    #[test]
+    fn position_is_in_degrees() {
+        let counter = SimCounter::new();
+        let encoder = Encoder::with_counter(counter.clone(), TICKS_PER_REVOLUTION, false);
+        counter.add(400);
+        assert_eq!(encoder.get_raw_position(), 400);
+        assert_eq!(encoder.get_position(), 100);
+        counter.add(-800);
+        assert_eq!(encoder.get_position(), -100);
+    }
+
+    #[test]
+    fn invert_negates_the_count() {
+        let counter = SimCounter::new();
+        let encoder = Encoder::with_counter(counter.clone(), TICKS_PER_REVOLUTION, true);
+        counter.add(400);
+        assert_eq!(encoder.get_raw_position(), -400);
+        assert_eq!(encoder.get_position(), -100);
+    }
+
+    #[test]
+    fn set_position_sets_the_count() {
+        for invert in [false, true] {
+            let counter = SimCounter::new();
+            let encoder = Encoder::with_counter(counter.clone(), TICKS_PER_REVOLUTION, invert);
+            encoder.set_position(90);
+            assert_eq!(counter.get_count(), if invert { -360 } else { 360 });
+            assert_eq!(encoder.get_position(), 90);
+        }
+    }
+
+    #[test]
+    fn clones_share_the_counter() {
+        let counter = SimCounter::new();
+        let encoder = Encoder::with_counter(counter.clone(), TICKS_PER_REVOLUTION, false);
+        let clone = encoder.clone();
+        counter.add(40);
+        assert_eq!(clone.get_position(), 10);
     }
 }
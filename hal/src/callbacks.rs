@@ -0,0 +1,119 @@
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+type Callback<T> = Arc<dyn Fn(T) + Send + Sync>;
+
+// Callback registry, clones share the callbacks. The callbacks run without the registry locked so
+// a callback can add callbacks or call into whatever owns the registry.
+pub struct Callbacks<T> {
+    callbacks: Arc<Mutex<Vec<Callback<T>>>>,
+}
+
+impl<T: Clone + Send + 'static> Callbacks<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&self, callback: impl Fn(T) + Send + Sync + 'static) {
+        self.callbacks.lock().unwrap().push(Arc::new(callback));
+    }
+
+    // values are sent for as long as the receiver is kept
+    pub fn subscribe(&self) -> Receiver<T> {
+        let (tx, rx) = channel();
+        self.add(move |value| {
+            let _ = tx.send(value);
+        });
+        rx
+    }
+
+    // a callback added while calling gets the next value
+    pub fn call(&self, value: T) {
+        let callbacks = self.callbacks.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(value.clone());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.callbacks.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Callbacks<T> {
+    fn clone(&self) -> Self {
+        Self {
+            callbacks: self.callbacks.clone(),
+        }
+    }
+}
+
+impl<T> Default for Callbacks<T> {
+    fn default() -> Self {
+        Self {
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Callbacks<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Callbacks")
+            .field("len", &self.callbacks.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    #[test]
+    fn calls_every_callback() {
+        let callbacks = Callbacks::new();
+        let first = callbacks.subscribe();
+        let second = callbacks.clone().subscribe();
+        callbacks.call(7);
+        assert_eq!(first.try_recv(), Ok(7));
+        assert_eq!(second.try_recv(), Ok(7));
+    }
+
+    #[test]
+    fn callbacks_can_reenter() {
+        let callbacks = Callbacks::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        {
+            let registry = callbacks.clone();
+            let calls = calls.clone();
+            callbacks.add(move |value: u32| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                // would deadlock if the registry were locked while calling
+                registry.add(|_| {});
+                if value > 0 {
+                    registry.call(value - 1);
+                }
+            });
+        }
+        callbacks.call(2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(callbacks.len(), 4);
+    }
+
+    #[test]
+    fn dropped_receivers_are_ignored() {
+        let callbacks = Callbacks::new();
+        drop(callbacks.subscribe());
+        let kept = callbacks.subscribe();
+        callbacks.call(1);
+        assert_eq!(kept.try_recv(), Ok(1));
+    }
+}
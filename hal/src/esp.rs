@@ -0,0 +1,232 @@
+use std::cmp::min;
+use std::ptr;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use esp_idf_hal::delay::TickType;
+use esp_idf_hal::gpio::AnyInputPin;
+use esp_idf_hal::gpio::InputPin;
+use esp_idf_hal::gpio::Output;
+use esp_idf_hal::gpio::Pin;
+use esp_idf_hal::gpio::PinDriver;
+use esp_idf_hal::i2c::I2cDriver;
+use esp_idf_hal::ledc::LedcDriver;
+use esp_idf_hal::pcnt::*;
+use esp_idf_hal::peripheral::Peripheral;
+use esp_idf_hal::uart::UartRxDriver;
+use esp_idf_hal::uart::UartTxDriver;
+use esp_idf_svc::timer::EspTaskTimerService;
+use esp_idf_svc::timer::EspTimer;
+use esp_idf_sys::vTaskPrioritySet;
+use esp_idf_sys::EspError;
+use esp_idf_sys::TaskHandle_t;
+use esp_idf_sys::ESP_TASK_PRIO_MAX;
+
+use crate::DigitalOutput;
+use crate::HalError;
+use crate::I2cBus;
+use crate::Priority;
+use crate::PwmOutput;
+use crate::QuadratureCounter;
+use crate::SerialRead;
+use crate::SerialWrite;
+use crate::Timer;
+
+impl<'d> PwmOutput for LedcDriver<'d> {
+    fn get_max_duty(&self) -> u32 {
+        LedcDriver::get_max_duty(self)
+    }
+
+    fn set_duty(&mut self, duty: u32) -> Result<(), HalError> {
+        Ok(LedcDriver::set_duty(self, duty)?)
+    }
+}
+
+impl<'d, T: Pin> DigitalOutput for PinDriver<'d, T, Output> {
+    fn set_high(&mut self) -> Result<(), HalError> {
+        Ok(PinDriver::set_high(self)?)
+    }
+
+    fn set_low(&mut self) -> Result<(), HalError> {
+        Ok(PinDriver::set_low(self)?)
+    }
+}
+
+impl<'d> SerialRead for UartRxDriver<'d> {
+    fn read(&mut self, buffer: &mut [u8], timeout: Duration) -> Result<usize, HalError> {
+        Ok(UartRxDriver::read(self, buffer, TickType::from(timeout).0)?)
+    }
+}
+
+impl<'d> SerialWrite for UartTxDriver<'d> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), HalError> {
+        let mut written = 0;
+        while written < bytes.len() {
+            written += UartTxDriver::write(self, &bytes[written..])?;
+        }
+        Ok(())
+    }
+}
+
+// a device that holds the clock longer than this is stuck
+const I2C_TIMEOUT: Duration = Duration::from_millis(100);
+
+impl<'d> I2cBus for I2cDriver<'d> {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), HalError> {
+        Ok(I2cDriver::write(
+            self,
+            address,
+            bytes,
+            TickType::from(I2C_TIMEOUT).0,
+        )?)
+    }
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), HalError> {
+        Ok(I2cDriver::write_read(
+            self,
+            address,
+            bytes,
+            buffer,
+            TickType::from(I2C_TIMEOUT).0,
+        )?)
+    }
+}
+
+impl Timer for EspTimer {
+    fn every(&self, interval: Duration) -> Result<(), HalError> {
+        Ok(EspTimer::every(self, interval)?)
+    }
+
+    fn cancel(&self) -> Result<bool, HalError> {
+        Ok(EspTimer::cancel(self)?)
+    }
+
+    fn is_scheduled(&self) -> Result<bool, HalError> {
+        Ok(EspTimer::is_scheduled(self)?)
+    }
+}
+
+pub fn timer(callback: impl FnMut() + Send + 'static) -> Result<Box<dyn Timer>, HalError> {
+    Ok(Box::new(EspTaskTimerService::new()?.timer(callback)?))
+}
+
+pub fn spawn<F, T>(
+    name: &str,
+    stack_size: usize,
+    priority: Priority,
+    f: F,
+) -> Result<JoinHandle<T>, std::io::Error>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new()
+        .name(name.into())
+        .stack_size(stack_size)
+        .spawn(move || {
+            let priority = match priority {
+                Priority::Default => None,
+                Priority::Medium => Some(ESP_TASK_PRIO_MAX / 2),
+                Priority::High => Some(ESP_TASK_PRIO_MAX - 2),
+            };
+            if let Some(priority) = priority {
+                unsafe { vTaskPrioritySet(ptr::null_mut() as TaskHandle_t, priority) }
+            }
+            f()
+        })
+}
+
+// PCNT based quadrature counter, the PCNT unit only counts to +/-100 before the count is
+// accumulated into `position` by the limit event.
+pub struct PcntCounter<'d> {
+    pcnt: PcntDriver<'d>,
+    position: Arc<AtomicI64>,
+}
+
+const POS_LIMIT: i16 = 100;
+const NEG_LIMIT: i16 = -100;
+
+impl<'d> PcntCounter<'d> {
+    pub fn new<PCNT: Pcnt>(
+        pcnt: impl Peripheral<P = PCNT> + 'd,
+        a_pin: impl Peripheral<P = impl InputPin> + 'd,
+        b_pin: impl Peripheral<P = impl InputPin> + 'd,
+    ) -> Result<Self, EspError> {
+        let mut pcnt = PcntDriver::new(
+            pcnt,
+            Some(a_pin),
+            Some(b_pin),
+            Option::<AnyInputPin>::None,
+            Option::<AnyInputPin>::None,
+        )?;
+        pcnt.channel_config(
+            PcntChannel::Channel0,
+            PinIndex::Pin0,
+            PinIndex::Pin1,
+            &PcntChannelConfig {
+                lctrl_mode: PcntControlMode::Reverse,
+                hctrl_mode: PcntControlMode::Keep,
+                pos_mode: PcntCountMode::Decrement,
+                neg_mode: PcntCountMode::Increment,
+                counter_h_lim: POS_LIMIT,
+                counter_l_lim: NEG_LIMIT,
+            },
+        )?;
+        pcnt.channel_config(
+            PcntChannel::Channel1,
+            PinIndex::Pin1,
+            PinIndex::Pin0,
+            &PcntChannelConfig {
+                lctrl_mode: PcntControlMode::Reverse,
+                hctrl_mode: PcntControlMode::Keep,
+                pos_mode: PcntCountMode::Increment,
+                neg_mode: PcntCountMode::Decrement,
+                counter_h_lim: POS_LIMIT,
+                counter_l_lim: NEG_LIMIT,
+            },
+        )?;
+        pcnt.set_filter_value(min(10 * 80, 1023))?;
+        pcnt.filter_enable()?;
+        pcnt.event_enable(PcntEvent::HighLimit)?;
+        pcnt.event_enable(PcntEvent::LowLimit)?;
+
+        let counter = PcntCounter {
+            pcnt,
+            position: Arc::new(AtomicI64::new(0)),
+        };
+
+        unsafe {
+            let position = counter.position.clone();
+            counter.pcnt.subscribe(move |status| {
+                let status = PcntEventType::from_repr_truncated(status);
+                if status.contains(PcntEvent::HighLimit) {
+                    position.fetch_add(POS_LIMIT as i64, Ordering::Relaxed);
+                }
+                if status.contains(PcntEvent::LowLimit) {
+                    position.fetch_add(NEG_LIMIT as i64, Ordering::Relaxed);
+                }
+            })?;
+        }
+        counter.pcnt.counter_pause()?;
+        counter.pcnt.counter_clear()?;
+        counter.pcnt.counter_resume()?;
+        Ok(counter)
+    }
+}
+
+impl<'d> QuadratureCounter for PcntCounter<'d> {
+    fn get_count(&self) -> i64 {
+        self.position.load(Ordering::Relaxed) + self.pcnt.get_counter_value().unwrap() as i64
+    }
+
+    fn set_count(&self, count: i64) {
+        // small race condition here but clearing the counter first means that we have *_LIMIT pcnt counts before we'd
+        // update the position
+        self.pcnt.counter_clear().unwrap();
+        self.position.store(count, Ordering::Relaxed);
+    }
+}
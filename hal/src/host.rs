@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::DigitalOutput;
+use crate::HalError;
+use crate::I2cBus;
+use crate::Priority;
+use crate::PwmOutput;
+use crate::QuadratureCounter;
+use crate::SerialRead;
+use crate::SerialWrite;
+use crate::Timer;
+
+// The host types are cheap to clone and clones share their state, keep a clone to look at (or
+// drive) what the code under test sees.
+
+#[derive(Debug, Clone)]
+pub struct SimPwm {
+    max_duty: u32,
+    duty: Arc<AtomicU32>,
+}
+
+impl SimPwm {
+    pub fn new(max_duty: u32) -> Self {
+        Self {
+            max_duty,
+            duty: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    pub fn get_duty(&self) -> u32 {
+        self.duty.load(Ordering::Relaxed)
+    }
+
+    pub fn get_percent(&self) -> f32 {
+        self.get_duty() as f32 * 100.0 / self.max_duty as f32
+    }
+}
+
+impl PwmOutput for SimPwm {
+    fn get_max_duty(&self) -> u32 {
+        self.max_duty
+    }
+
+    fn set_duty(&mut self, duty: u32) -> Result<(), HalError> {
+        self.duty.store(duty.min(self.max_duty), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SimPin {
+    high: Arc<AtomicBool>,
+}
+
+impl SimPin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.high.load(Ordering::Relaxed)
+    }
+}
+
+impl DigitalOutput for SimPin {
+    fn set_high(&mut self) -> Result<(), HalError> {
+        self.high.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), HalError> {
+        self.high.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SimCounter {
+    count: Arc<AtomicI64>,
+}
+
+impl SimCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&self, ticks: i64) {
+        self.count.fetch_add(ticks, Ordering::Relaxed);
+    }
+}
+
+impl QuadratureCounter for SimCounter {
+    fn get_count(&self) -> i64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn set_count(&self, count: i64) {
+        self.count.store(count, Ordering::Relaxed);
+    }
+}
+
+// Serial receiver fed through the sender returned by `SimSerial::channel`.
+pub struct SimSerial {
+    rx: Receiver<Vec<u8>>,
+    pending: VecDeque<u8>,
+}
+
+impl SimSerial {
+    pub fn channel() -> (Sender<Vec<u8>>, Self) {
+        let (tx, rx) = channel();
+        (
+            tx,
+            Self {
+                rx,
+                pending: VecDeque::new(),
+            },
+        )
+    }
+}
+
+impl SerialRead for SimSerial {
+    fn read(&mut self, buffer: &mut [u8], timeout: Duration) -> Result<usize, HalError> {
+        if self.pending.is_empty() {
+            match self.rx.recv_timeout(timeout) {
+                Ok(bytes) => self.pending.extend(bytes),
+                // a closed sender looks like a quiet line
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                    return Ok(0)
+                }
+            }
+        }
+        while let Ok(bytes) = self.rx.try_recv() {
+            self.pending.extend(bytes);
+        }
+        let count = buffer.len().min(self.pending.len());
+        for (b, p) in buffer.iter_mut().zip(self.pending.drain(..count)) {
+            *b = p;
+        }
+        Ok(count)
+    }
+}
+
+// Serial transmitter that keeps what was written.
+#[derive(Debug, Clone, Default)]
+pub struct SimSerialTx {
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SimSerialTx {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the bytes written since the last take
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.written.lock().unwrap())
+    }
+}
+
+impl SerialWrite for SimSerialTx {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), HalError> {
+        self.written.lock().unwrap().extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct SimI2cDevice {
+    register_width: usize, // bytes of register address, 1 or 2
+    pointer: usize,
+    registers: Vec<u8>,
+    read_only: Vec<bool>,
+}
+
+impl SimI2cDevice {
+    // sets the register pointer from the address bytes, the bytes after it are written
+    fn write(&mut self, bytes: &[u8]) {
+        let width = self.register_width.min(bytes.len());
+        self.pointer = bytes[..width]
+            .iter()
+            .fold(0, |pointer, byte| pointer << 8 | *byte as usize);
+        for byte in &bytes[width..] {
+            if !self.read_only[self.pointer] {
+                self.registers[self.pointer] = *byte;
+            }
+            self.pointer = (self.pointer + 1) % self.registers.len();
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer {
+            *byte = self.registers[self.pointer];
+            self.pointer = (self.pointer + 1) % self.registers.len();
+        }
+    }
+}
+
+// I2C bus with register mapped devices, the register pointer increments on reads and writes.
+// Registers only change when written, set them to what the device would measure.
+#[derive(Debug, Clone, Default)]
+pub struct SimI2c {
+    devices: Arc<Mutex<HashMap<u8, SimI2cDevice>>>,
+}
+
+impl SimI2c {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `register_width` is 1 for 8 bit register addresses, 2 for 16 bit
+    pub fn add_device(&self, address: u8, register_width: usize) {
+        self.devices.lock().unwrap().insert(
+            address,
+            SimI2cDevice {
+                register_width,
+                pointer: 0,
+                registers: vec![0; 1 << (8 * register_width)],
+                read_only: vec![false; 1 << (8 * register_width)],
+            },
+        );
+    }
+
+    pub fn set_registers(&self, address: u8, register: u16, bytes: &[u8]) {
+        let mut devices = self.devices.lock().unwrap();
+        let device = devices.get_mut(&address).expect("no such device");
+        let start = register as usize;
+        device.registers[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+
+    // status and result registers, the bus ignores writes to them
+    pub fn set_read_only(&self, address: u8, register: u16, count: usize) {
+        let mut devices = self.devices.lock().unwrap();
+        let device = devices.get_mut(&address).expect("no such device");
+        let start = register as usize;
+        device.read_only[start..start + count].fill(true);
+    }
+
+    pub fn registers(&self, address: u8, register: u16, count: usize) -> Vec<u8> {
+        let devices = self.devices.lock().unwrap();
+        let device = devices.get(&address).expect("no such device");
+        let start = register as usize;
+        device.registers[start..start + count].to_vec()
+    }
+
+    fn device<T>(
+        &self,
+        address: u8,
+        f: impl FnOnce(&mut SimI2cDevice) -> T,
+    ) -> Result<T, HalError> {
+        match self.devices.lock().unwrap().get_mut(&address) {
+            Some(device) => Ok(f(device)),
+            // nothing acknowledged the address
+            None => Err(std::io::Error::new(
+                ErrorKind::NotConnected,
+                format!("no i2c device at {address:#04x}"),
+            )
+            .into()),
+        }
+    }
+}
+
+impl I2cBus for SimI2c {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), HalError> {
+        self.device(address, |device| device.write(bytes))
+    }
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), HalError> {
+        self.device(address, |device| {
+            device.write(bytes);
+            device.read(buffer);
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Schedule {
+    interval: Option<Duration>,
+    next: Option<Instant>,
+    shutdown: bool,
+}
+
+// Thread based timer, the callback runs on the timer thread like the esp task timer service.
+pub struct HostTimer {
+    schedule: Arc<(Mutex<Schedule>, Condvar)>,
+}
+
+impl Timer for HostTimer {
+    fn every(&self, interval: Duration) -> Result<(), HalError> {
+        let (lock, cvar) = &*self.schedule;
+        let mut schedule = lock.lock().unwrap();
+        schedule.interval = Some(interval);
+        schedule.next = Some(Instant::now() + interval);
+        cvar.notify_one();
+        Ok(())
+    }
+
+    fn cancel(&self) -> Result<bool, HalError> {
+        let (lock, cvar) = &*self.schedule;
+        let mut schedule = lock.lock().unwrap();
+        let scheduled = schedule.next.is_some();
+        schedule.interval = None;
+        schedule.next = None;
+        cvar.notify_one();
+        Ok(scheduled)
+    }
+
+    fn is_scheduled(&self) -> Result<bool, HalError> {
+        Ok(self.schedule.0.lock().unwrap().next.is_some())
+    }
+}
+
+impl Drop for HostTimer {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.schedule;
+        lock.lock().unwrap().shutdown = true;
+        cvar.notify_one();
+    }
+}
+
+pub fn timer(mut callback: impl FnMut() + Send + 'static) -> Result<Box<dyn Timer>, HalError> {
+    let schedule = Arc::new((Mutex::new(Schedule::default()), Condvar::new()));
+    {
+        let schedule = schedule.clone();
+        thread::Builder::new().name("timer".into()).spawn(move || {
+            let (lock, cvar) = &*schedule;
+            let mut guard = lock.lock().unwrap();
+            loop {
+                if guard.shutdown {
+                    return;
+                }
+                match guard.next {
+                    None => guard = cvar.wait(guard).unwrap(),
+                    Some(next) => {
+                        let now = Instant::now();
+                        if now < next {
+                            guard = cvar.wait_timeout(guard, next - now).unwrap().0;
+                            continue;
+                        }
+                        guard.next = guard.interval.map(|i| next + i);
+                        drop(guard);
+                        callback();
+                        guard = lock.lock().unwrap();
+                    }
+                }
+            }
+        })?;
+    }
+    Ok(Box::new(HostTimer { schedule }))
+}
+
+// stack sizes are tuned for the esp and priorities are left to the host scheduler
+pub fn spawn<F, T>(
+    name: &str,
+    _stack_size: usize,
+    _priority: Priority,
+    f: F,
+) -> Result<JoinHandle<T>, std::io::Error>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new().name(name.into()).spawn(f)
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+mod callbacks;
+#[cfg(feature = "esp")]
+pub mod esp;
+#[cfg(feature = "host")]
+pub mod host;
+
+pub use callbacks::Callbacks;
+
+// The firmware uses the ESP-IDF backend, on the host (tests, simulator) the `host` backend is used.
+#[cfg(feature = "esp")]
+pub use esp::spawn;
+#[cfg(feature = "esp")]
+pub use esp::timer;
+#[cfg(all(feature = "host", not(feature = "esp")))]
+pub use host::spawn;
+#[cfg(all(feature = "host", not(feature = "esp")))]
+pub use host::timer;
+
+// PWM output with a duty cycle of 0..=get_max_duty()
+pub trait PwmOutput: Send {
+    fn get_max_duty(&self) -> u32;
+    fn set_duty(&mut self, duty: u32) -> Result<(), HalError>;
+}
+
+pub trait DigitalOutput: Send {
+    fn set_high(&mut self) -> Result<(), HalError>;
+    fn set_low(&mut self) -> Result<(), HalError>;
+}
+
+// Quadrature counter that keeps the full count in ticks.
+pub trait QuadratureCounter: Send + Sync {
+    fn get_count(&self) -> i64;
+    fn set_count(&self, count: i64);
+}
+
+// Periodic timer, the callback given when the timer was created runs on every expiry.
+pub trait Timer: Send {
+    fn every(&self, interval: Duration) -> Result<(), HalError>;
+    fn cancel(&self) -> Result<bool, HalError>;
+    fn is_scheduled(&self) -> Result<bool, HalError>;
+}
+
+// Serial receiver, returns the number of bytes read, 0 if nothing arrived before the timeout.
+pub trait SerialRead: Send {
+    fn read(&mut self, buffer: &mut [u8], timeout: Duration) -> Result<usize, HalError>;
+}
+
+// Serial transmitter, returns once all the bytes are queued.
+pub trait SerialWrite: Send {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), HalError>;
+}
+
+// I2C master, addresses are 7 bit. Register reads are a write of the register address followed by
+// a read in one transaction.
+pub trait I2cBus: Send {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), HalError>;
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), HalError>;
+}
+
+// One bus shared by several devices, each device gets a clone. Transactions are serialized, a
+// device that needs several in a row has to cope with others in between.
+#[derive(Clone)]
+pub struct SharedBus {
+    bus: Arc<Mutex<Box<dyn I2cBus>>>,
+}
+
+impl SharedBus {
+    pub fn new(bus: impl I2cBus + 'static) -> Self {
+        Self {
+            bus: Arc::new(Mutex::new(Box::new(bus))),
+        }
+    }
+}
+
+impl I2cBus for SharedBus {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), HalError> {
+        self.bus.lock().unwrap().write(address, bytes)
+    }
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), HalError> {
+        self.bus.lock().unwrap().write_read(address, bytes, buffer)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Default, // leave the priority as the system sets it
+    Medium,
+    High, // control loops
+}
+
+#[derive(Debug)]
+pub enum HalError {
+    #[cfg(feature = "esp")]
+    EspError(esp_idf_sys::EspError),
+    IOError(std::io::Error),
+}
+
+impl std::fmt::Display for HalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for HalError {}
+
+#[cfg(feature = "esp")]
+impl From<esp_idf_sys::EspError> for HalError {
+    fn from(e: esp_idf_sys::EspError) -> Self {
+        HalError::EspError(e)
+    }
+}
+
+impl From<std::io::Error> for HalError {
+    fn from(e: std::io::Error) -> Self {
+        HalError::IOError(e)
+    }
+}
@@ -11,6 +11,16 @@ use log::*;
 
 use lidar::Lidar;
 
+// finer bins than the rover to look at the returns, only clamped to the LD19's range
+const LIDAR_CONFIG: lidar::Config = lidar::Config {
+    resolution: 0.5,
+    filters: &[lidar::Filter::Range {
+        min: 20,
+        max: 12000,
+    }],
+    ..lidar::Config::DEFAULT
+};
+
 fn main() -> anyhow::Result<()> {
     esp_idf_sys::link_patches();
     // Bind the log crate to the ESP Logging facilities
@@ -32,7 +42,7 @@ fn main() -> anyhow::Result<()> {
     )?;
 
     info!("create lidar");
-    let mut lidar = Lidar::new(uart, peripherals.pins.gpio2.into());
+    let mut lidar = Lidar::new(uart, peripherals.pins.gpio2.into(), LIDAR_CONFIG);
     loop {
         info!("power lidar on");
         lidar.set_power_on();
@@ -45,12 +55,23 @@ fn main() -> anyhow::Result<()> {
         info!("wait for lidar to get a full sweep");
         delay.delay_ms(2000);
 
+        // needs open space around the rover
+        info!("learn the self-occlusion mask");
+        let mask = lidar.calibrate_mask(20, 150, 10);
+        let masked = mask.limits().iter().filter(|l| **l > 0).count();
+        info!("masked {masked} bins");
+
         // take readings once a second for a a bit
         for _ in 0..20 {
-            let left = lidar.get_range_left();
-            let front = lidar.get_range_front();
-            let right = lidar.get_range_right();
-            info!("l/f/r: {left:.2} / {front:.2} / {right:.2}");
+            let frame = lidar.get_frame();
+            let behind = lidar.range_in(135.0, 225.0);
+            let scan = lidar.latest_scan();
+            info!(
+                "{:?} behind: {behind:?} points: {}",
+                frame.ranges(),
+                scan.len()
+            );
+            info!("{:?} healthy: {}", lidar.stats(), lidar.is_healthy());
             delay.delay_ms(1000);
         }
 
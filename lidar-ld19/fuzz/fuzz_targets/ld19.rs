@@ -0,0 +1,44 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use lidar_ld19::Ld19Parser;
+use lidar_ld19::LidarFrame;
+use lidar_ld19::HEADER;
+use lidar_ld19::PACKET_SIZE;
+
+// Any garbage followed by two good packets, the parser never panics and the second packet comes
+// out as the last frame: anything that started in the garbage ends before the second header.
+fuzz_target!(|garbage: &[u8]| {
+    let first = packet(0);
+    let second = packet(1000);
+    let mut parser = Ld19Parser::new();
+    let mut last = None;
+    for byte in garbage.iter().chain(&first).chain(&second) {
+        if let Ok(Some(frame)) = parser.add_byte(*byte) {
+            last = Some(frame);
+        }
+    }
+    let frame = last.expect("no frame after the good packets");
+    assert_eq!(frame.start_angle, 1000);
+    assert_eq!(frame.end_angle, 1880);
+    assert_eq!(frame.points[11].angle, 1880);
+});
+
+// no header bytes inside, so the only place a packet can start is its own header
+fn packet(start_angle: u16) -> [u8; PACKET_SIZE] {
+    let mut frame = LidarFrame {
+        speed: 3600,
+        start_angle,
+        end_angle: start_angle + 880,
+        timestamp: 7,
+        ..LidarFrame::default()
+    };
+    for (i, point) in frame.points.iter_mut().enumerate() {
+        point.distance = 1000 + i as u16;
+        point.confidence = 200;
+    }
+    let packet = frame.to_bytes();
+    assert_ne!(packet[PACKET_SIZE - 1], HEADER);
+    packet
+}
@@ -0,0 +1,284 @@
+#![cfg_attr(not(test), no_std)]
+
+// LD19 packets, 230400 baud, the LD06 sends the same:
+//   0x54 0x2c speed:u16 start_angle:u16 12 * (distance:u16 confidence:u8) end_angle:u16
+//   timestamp:u16 crc8
+// little endian, angles in centidegrees, the timestamp in ms wraps at 30000.
+pub const HEADER: u8 = 0x54;
+pub const VER_LEN: u8 = 0x2c;
+pub const POINTS: usize = 12;
+pub const PACKET_SIZE: usize = 11 + POINTS * 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Crc,     // a packet failed its check
+    Invalid, // bytes that can't be part of a packet, the parser resyncs
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LidarPoint {
+    pub angle: u16,    // centidegrees, spread evenly from the start to the end angle
+    pub distance: u16, // mm
+    pub confidence: u8,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LidarFrame {
+    pub speed: u16, // degrees per second
+    pub start_angle: u16,
+    pub points: [LidarPoint; POINTS],
+    pub end_angle: u16,
+    pub timestamp: u16,
+}
+
+impl LidarFrame {
+    // `bytes` is a whole packet, the CRC is not checked
+    pub fn from_bytes(bytes: &[u8; PACKET_SIZE]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let start_angle = u16_at(4);
+        let end_angle = u16_at(6 + POINTS * 3);
+        // the end angle is past 0 when the packet crosses it
+        let span = (end_angle as u32 + 36000 - start_angle as u32) % 36000;
+        let mut points = [LidarPoint::default(); POINTS];
+        for (i, point) in points.iter_mut().enumerate() {
+            let offset = 6 + i * 3;
+            *point = LidarPoint {
+                angle: ((start_angle as u32 + span * i as u32 / (POINTS as u32 - 1)) % 36000)
+                    as u16,
+                distance: u16_at(offset),
+                confidence: bytes[offset + 2],
+            };
+        }
+        Self {
+            speed: u16_at(2),
+            start_angle,
+            points,
+            end_angle,
+            timestamp: u16_at(8 + POINTS * 3),
+        }
+    }
+
+    // the packet the sensor would send, the points' angles come from the start and end angles
+    pub fn to_bytes(&self) -> [u8; PACKET_SIZE] {
+        let mut bytes = [0u8; PACKET_SIZE];
+        bytes[0] = HEADER;
+        bytes[1] = VER_LEN;
+        bytes[2..4].copy_from_slice(&self.speed.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.start_angle.to_le_bytes());
+        for (i, point) in self.points.iter().enumerate() {
+            let offset = 6 + i * 3;
+            bytes[offset..offset + 2].copy_from_slice(&point.distance.to_le_bytes());
+            bytes[offset + 2] = point.confidence;
+        }
+        bytes[6 + POINTS * 3..8 + POINTS * 3].copy_from_slice(&self.end_angle.to_le_bytes());
+        bytes[8 + POINTS * 3..10 + POINTS * 3].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes[PACKET_SIZE - 1] = crc8(&bytes[..PACKET_SIZE - 1]);
+        bytes
+    }
+}
+
+// Byte at a time parser. After a CRC failure it carries on from the next header in what it has
+// already read, so a packet that started inside a bad one isn't lost.
+pub struct Ld19Parser {
+    buffer: [u8; PACKET_SIZE],
+    len: usize,
+}
+
+impl Ld19Parser {
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; PACKET_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn add_byte(&mut self, byte: u8) -> Result<Option<LidarFrame>, Error> {
+        match (self.len, byte) {
+            (0, HEADER) | (1, VER_LEN) => {}
+            (0, _) => return Ok(None),
+            // a repeated header byte can still start the packet
+            (1, HEADER) => return Ok(None),
+            (1, _) => {
+                self.len = 0;
+                return Err(Error::Invalid);
+            }
+            _ => {}
+        }
+        self.buffer[self.len] = byte;
+        self.len += 1;
+        if self.len < PACKET_SIZE {
+            return Ok(None);
+        }
+        if crc8(&self.buffer[..PACKET_SIZE - 1]) == self.buffer[PACKET_SIZE - 1] {
+            self.len = 0;
+            return Ok(Some(LidarFrame::from_bytes(&self.buffer)));
+        }
+        self.resync();
+        Err(Error::Crc)
+    }
+
+    // waits for the next header
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    // keeps the bytes from the next possible header on
+    fn resync(&mut self) {
+        let next = (1..PACKET_SIZE).find(|&i| {
+            self.buffer[i] == HEADER && (i + 1 == PACKET_SIZE || self.buffer[i + 1] == VER_LEN)
+        });
+        self.len = match next {
+            Some(start) => {
+                self.buffer.copy_within(start.., 0);
+                PACKET_SIZE - start
+            }
+            None => 0,
+        };
+    }
+}
+
+impl Default for Ld19Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// CRC-8 with polynomial 0x4d as in the LD19 datasheet
+pub fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = match crc & 0x80 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x4d,
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // from the LDROBOT manual
+    const PACKET: [u8; PACKET_SIZE] = [
+        0x54, 0x2c, 0x68, 0x08, 0xab, 0x7e, 0xe0, 0x00, 0xe4, 0xdc, 0x00, 0xe2, 0xd9, 0x00, 0xe5,
+        0xd5, 0x00, 0xe3, 0xd3, 0x00, 0xe4, 0xd0, 0x00, 0xe9, 0xcd, 0x00, 0xe4, 0xca, 0x00, 0xe2,
+        0xc7, 0x00, 0xe9, 0xc5, 0x00, 0xe5, 0xc2, 0x00, 0xe5, 0xc0, 0x00, 0xe5, 0xbe, 0x82, 0x3a,
+        0x1a, 0x50,
+    ];
+
+    fn parse(parser: &mut Ld19Parser, bytes: &[u8]) -> (Vec<LidarFrame>, Vec<Error>) {
+        let mut frames = Vec::new();
+        let mut errors = Vec::new();
+        for byte in bytes {
+            match parser.add_byte(*byte) {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+        (frames, errors)
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc8(&PACKET[..PACKET_SIZE - 1]), 0x50);
+    }
+
+    #[test]
+    fn good_frame() {
+        let (frames, errors) = parse(&mut Ld19Parser::new(), &PACKET);
+        assert!(errors.is_empty());
+        assert_eq!(frames.len(), 1);
+        let frame = frames[0];
+        assert_eq!(frame.speed, 2152);
+        assert_eq!(frame.start_angle, 32427);
+        assert_eq!(frame.end_angle, 33470);
+        assert_eq!(frame.timestamp, 6714);
+        assert_eq!(
+            frame.points[0],
+            LidarPoint {
+                angle: 32427,
+                distance: 224,
+                confidence: 0xe4,
+            }
+        );
+        // 1043 centidegrees over 11 steps
+        assert_eq!(frame.points[1].angle, 32427 + 94);
+        assert_eq!(frame.points[11].angle, 33470);
+        assert_eq!(frame.points[11].distance, 192);
+    }
+
+    #[test]
+    fn bad_crc() {
+        let mut packet = PACKET;
+        packet[10] ^= 0x01;
+        let mut parser = Ld19Parser::new();
+        let (frames, errors) = parse(&mut parser, &packet);
+        assert!(frames.is_empty());
+        assert_eq!(errors, [Error::Crc]);
+        // and carries on with the next one
+        let (frames, errors) = parse(&mut parser, &PACKET);
+        assert_eq!(frames.len(), 1);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn garbage_before_the_header() {
+        let mut bytes = vec![0x00, 0xff, 0x54, 0x12, 0x2c, 0x54];
+        bytes.extend(PACKET);
+        let (frames, errors) = parse(&mut Ld19Parser::new(), &bytes);
+        assert_eq!(frames.len(), 1);
+        // the 0x54 0x12
+        assert_eq!(errors, [Error::Invalid]);
+    }
+
+    #[test]
+    fn resyncs_mid_packet() {
+        // a packet cut short by the next one, its CRC check takes the start of the next packet
+        let mut bytes = PACKET[..20].to_vec();
+        bytes.extend(PACKET);
+        let (frames, errors) = parse(&mut Ld19Parser::new(), &bytes);
+        assert_eq!(errors, [Error::Crc]);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0], LidarFrame::from_bytes(&PACKET));
+    }
+
+    #[test]
+    fn reset_drops_a_partial_packet() {
+        let mut parser = Ld19Parser::new();
+        parse(&mut parser, &PACKET[..20]);
+        parser.reset();
+        let (frames, errors) = parse(&mut parser, &PACKET);
+        assert_eq!(frames.len(), 1);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(LidarFrame::from_bytes(&PACKET).to_bytes(), PACKET);
+    }
+
+    #[test]
+    fn angles_across_0() {
+        let frame = LidarFrame {
+            start_angle: 35500,
+            end_angle: 600,
+            ..LidarFrame::default()
+        };
+        let frame = LidarFrame::from_bytes(&frame.to_bytes());
+        assert_eq!(frame.points[0].angle, 35500);
+        assert_eq!(frame.points[1].angle, 35600);
+        assert_eq!(frame.points[5].angle, 0);
+        assert_eq!(frame.points[11].angle, 600);
+    }
+}
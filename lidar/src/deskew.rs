@@ -0,0 +1,50 @@
+use crate::Mount;
+use crate::Point;
+use crate::ScanPoint;
+
+// Where the rover was in the odometry frame, mm and radians counter clockwise from the x axis like
+// `differential_drive::Pose`. The lidar doesn't depend on the drive, the caller converts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pose {
+    pub x: f32,
+    pub y: f32,
+    pub heading: f32,
+}
+
+impl Pose {
+    // a base frame point at this pose in the odometry frame
+    pub fn to_odometry(&self, point: Point) -> Point {
+        let (sin, cos) = self.heading.sin_cos();
+        Point {
+            x: self.x + point.x * cos - point.y * sin,
+            y: self.y + point.x * sin + point.y * cos,
+        }
+    }
+
+    // an odometry frame point in the base frame at this pose
+    pub fn to_base(&self, point: Point) -> Point {
+        let (sin, cos) = self.heading.sin_cos();
+        let (x, y) = (point.x - self.x, point.y - self.y);
+        Point {
+            x: x * cos + y * sin,
+            y: y * cos - x * sin,
+        }
+    }
+}
+
+// A point measured at `pose` as it's seen from `reference`, the angle is still from the rover's
+// front and the distance from the sensor. It keeps the time it was measured.
+pub(crate) fn deskew_point(
+    mount: &Mount,
+    point: &ScanPoint,
+    pose: &Pose,
+    reference: &Pose,
+) -> ScanPoint {
+    let base = mount.point(point.angle, point.distance);
+    let (angle, distance) = mount.polar(reference.to_base(pose.to_odometry(base)));
+    ScanPoint {
+        angle,
+        distance,
+        ..*point
+    }
+}
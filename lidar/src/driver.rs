@@ -0,0 +1,539 @@
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU16;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+#[cfg(feature = "esp")]
+use esp_idf_hal::gpio::AnyOutputPin;
+#[cfg(feature = "esp")]
+use esp_idf_hal::gpio::PinDriver;
+#[cfg(feature = "esp")]
+use esp_idf_hal::ledc::LedcDriver;
+#[cfg(feature = "esp")]
+use esp_idf_hal::uart::UartRxDriver;
+use hal::DigitalOutput;
+use hal::PwmOutput;
+use hal::SerialRead;
+use hal::SerialWrite;
+use log::warn;
+
+use crate::Calibration;
+use crate::Config;
+use crate::Data;
+use crate::Frame;
+use crate::Ld19;
+use crate::Mask;
+use crate::Pose;
+use crate::PowerState;
+use crate::PowerStatus;
+use crate::Protocol;
+use crate::Published;
+use crate::RangeScanner;
+use crate::Recorder;
+use crate::Scan;
+use crate::Sector;
+use crate::SpeedConfig;
+use crate::SpeedControl;
+use crate::Stats;
+
+// LD19 default speed is 10Hz
+const REVOLUTION: Duration = Duration::from_millis(100);
+
+// Reads return when the buffer is full or after the timeout, at 230400 baud the buffer fills in
+// about 11ms. A byte is 10 bits on the wire.
+const READ_SIZE: usize = 256;
+const READ_TIMEOUT: Duration = Duration::from_millis(10);
+const BYTE_TIME: Duration = Duration::from_nanos(1_000_000_000 / 23040);
+
+type Recording = Recorder<Box<dyn Write + Send>>;
+
+pub struct Lidar<'a> {
+    power: Box<dyn DigitalOutput + 'a>,
+    data: Arc<Mutex<Data>>, // only the receive thread and setup take this
+    published: Arc<Published>,
+    recording: Arc<Mutex<Option<Recording>>>,
+    running: Arc<AtomicBool>,
+    status: PowerStatus,
+    target_speed: Arc<AtomicU16>,
+    speed_config: SpeedConfig,
+}
+
+impl<'a> Lidar<'a> {
+    #[cfg(feature = "esp")]
+    pub fn new(serial: UartRxDriver<'static>, power: AnyOutputPin, config: Config) -> Self {
+        // TODO: how can I set this high before making it an output.
+        let power = PinDriver::output_od(power).unwrap();
+        Self::with_driver(serial, power, config)
+    }
+
+    // with the speed controlled through the LD19's PWM input
+    #[cfg(feature = "esp")]
+    pub fn with_pwm(
+        serial: UartRxDriver<'static>,
+        power: AnyOutputPin,
+        pwm: LedcDriver<'static>,
+        config: Config,
+    ) -> Self {
+        let power = PinDriver::output_od(power).unwrap();
+        Self::with_speed_control(serial, power, pwm, config)
+    }
+
+    // power is active low, it is set high (off) here
+    pub fn with_driver(
+        serial: impl SerialRead + 'static,
+        power: impl DigitalOutput + 'a,
+        config: Config,
+    ) -> Self {
+        Self::start(serial, None, power, Ld19::new(), None, config)
+    }
+
+    // another lidar model on the serial port, like the LD06
+    pub fn with_protocol(
+        serial: impl SerialRead + 'static,
+        power: impl DigitalOutput + 'a,
+        protocol: impl Protocol + 'static,
+        config: Config,
+    ) -> Self {
+        Self::start(serial, None, power, protocol, None, config)
+    }
+
+    // a lidar that only streams on request, like the RPLidar, `requests` is the UART's TX
+    pub fn with_requests(
+        serial: impl SerialRead + 'static,
+        requests: impl SerialWrite + 'static,
+        power: impl DigitalOutput + 'a,
+        protocol: impl Protocol + 'static,
+        config: Config,
+    ) -> Self {
+        Self::start(
+            serial,
+            Some(Box::new(requests)),
+            power,
+            protocol,
+            None,
+            config,
+        )
+    }
+
+    pub fn with_speed_control(
+        serial: impl SerialRead + 'static,
+        power: impl DigitalOutput + 'a,
+        pwm: impl PwmOutput + 'static,
+        config: Config,
+    ) -> Self {
+        let speed_control = SpeedControl::new(Box::new(pwm), config.speed.unwrap_or_default());
+        Self::start(
+            serial,
+            None,
+            power,
+            Ld19::new(),
+            Some(speed_control),
+            config,
+        )
+    }
+
+    fn start(
+        mut serial: impl SerialRead + 'static,
+        mut requests: Option<Box<dyn SerialWrite>>,
+        mut power: impl DigitalOutput + 'a,
+        mut protocol: impl Protocol + 'static,
+        mut speed_control: Option<SpeedControl>,
+        config: Config,
+    ) -> Self {
+        let data = Arc::new(Mutex::new(Data::new(config)));
+        let published = Arc::new(Published::new(config));
+        let recording: Arc<Mutex<Option<Recording>>> = Arc::new(Mutex::new(None));
+        let running = Arc::new(AtomicBool::new(false));
+        let status = PowerStatus::new();
+        let speed_config = config.speed.unwrap_or_default();
+        let target_speed = Arc::new(AtomicU16::new(speed_config.speed));
+
+        {
+            let data = data.clone();
+            let published = published.clone();
+            let recording = recording.clone();
+            let running = running.clone();
+            let status = status.clone();
+            let target_speed = target_speed.clone();
+            _ = thread::Builder::new()
+                .stack_size(8192)
+                .name("lidar".into())
+                .spawn(move || {
+                    let mut buffer = [0u8; READ_SIZE];
+                    let mut last_published = Instant::now();
+                    let mut powered = false;
+                    loop {
+                        let on = running.load(Ordering::SeqCst);
+                        if on != powered {
+                            powered = on;
+                            // a packet cut short by the power change is dropped
+                            protocol.reset();
+                            let request = match on {
+                                true => protocol.start_request(),
+                                false => protocol.stop_request(),
+                            };
+                            if let Some(requests) = requests.as_mut() {
+                                if !request.is_empty() {
+                                    if let Err(err) = requests.write(request) {
+                                        warn!("lidar request failed: {err:?}");
+                                    }
+                                }
+                            }
+                            // synced once it's up to speed and has done a revolution
+                            status.power(on);
+                            if on {
+                                if let Some(speed_control) = speed_control.as_mut() {
+                                    speed_control.reset();
+                                }
+                            } else {
+                                let mut data = data.lock().unwrap();
+                                data.reset();
+                                published.publish(data.snapshot());
+                            }
+                        }
+                        if !on {
+                            // wait for lidar to be enabled
+                            thread::sleep(Duration::from_millis(100));
+                            continue;
+                        }
+                        let count = match serial.read(&mut buffer, READ_TIMEOUT) {
+                            Ok(count) => count,
+                            Err(err) => {
+                                warn!("lidar read failed: {err:?}");
+                                data.lock().unwrap().add_error();
+                                thread::sleep(READ_TIMEOUT);
+                                continue;
+                            }
+                        };
+                        let read = Instant::now();
+                        // recorded once the data is unlocked, the writer may block
+                        let recording_on = recording.lock().unwrap().is_some();
+                        let mut packets = Vec::new();
+                        let mut data = data.lock().unwrap();
+                        let mut revolution = false;
+                        for (i, byte) in buffer[..count].iter().enumerate() {
+                            match protocol.add_byte(*byte) {
+                                Err(err) => {
+                                    warn!("lidar error: {err:?}");
+                                    data.add_error();
+                                }
+                                Ok(Some(packet)) => {
+                                    // the bytes after this one were still on the wire
+                                    let received = read - BYTE_TIME * (count - 1 - i) as u32;
+                                    revolution |= data.update(&packet, received);
+                                    if recording_on {
+                                        packets.push((packet, received));
+                                    }
+                                }
+                                Ok(None) => {}
+                            }
+                        }
+                        // also publish when the revolutions stop so the stats show it
+                        match speed_control.as_mut() {
+                            Some(speed_control) if revolution => {
+                                let target = target_speed.load(Ordering::SeqCst);
+                                speed_control.update(target, data.stats().speed);
+                            }
+                            _ => {}
+                        }
+                        if revolution || read - last_published >= REVOLUTION {
+                            published.publish(data.snapshot());
+                            last_published = read;
+                        }
+                        status.update(data.is_healthy());
+                        drop(data);
+                        if !packets.is_empty() {
+                            let mut recording = recording.lock().unwrap();
+                            for (packet, received) in &packets {
+                                let Some(recorder) = recording.as_mut() else {
+                                    break;
+                                };
+                                if let Err(err) = recorder.record(packet, *received) {
+                                    warn!("lidar recording stopped: {err:?}");
+                                    *recording = None;
+                                }
+                            }
+                        }
+                    }
+                })
+                .unwrap();
+        }
+
+        power.set_high().unwrap();
+
+        Lidar {
+            power: Box::new(power),
+            data,
+            published,
+            recording,
+            running,
+            status,
+            target_speed,
+            speed_config,
+        }
+    }
+
+    pub fn set_power_on(&mut self) {
+        self.power.set_low().unwrap();
+        self.running.store(true, Ordering::SeqCst)
+    }
+
+    pub fn set_power_off(&mut self) {
+        self.power.set_high().unwrap();
+        self.running.store(false, Ordering::SeqCst)
+    }
+
+    pub fn set_power(&mut self, value: bool) {
+        if value {
+            self.set_power_on()
+        } else {
+            self.set_power_off()
+        }
+    }
+
+    // degrees per second, clamped to the configured range, only has an effect with speed control
+    pub fn set_speed(&self, speed: u16) {
+        self.target_speed
+            .store(self.speed_config.clamp(speed), Ordering::SeqCst);
+    }
+
+    // see `SpeedConfig::for_motion`
+    pub fn set_motion(&self, motion: f32) {
+        self.target_speed
+            .store(self.speed_config.for_motion(motion), Ordering::SeqCst);
+    }
+
+    pub fn target_speed(&self) -> u16 {
+        self.target_speed.load(Ordering::SeqCst)
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.status.state() == PowerState::Synced
+    }
+
+    pub fn power_state(&self) -> PowerState {
+        self.status.state()
+    }
+
+    pub fn power_status(&self) -> &PowerStatus {
+        &self.status
+    }
+
+    pub fn range_in(&self, start_deg: f32, end_deg: f32) -> Option<u16> {
+        self.published.read().range_in(start_deg, end_deg)
+    }
+
+    pub fn frame(&self, sectors: &[Sector]) -> Frame {
+        self.published.read().frame(sectors)
+    }
+
+    pub fn get_frame(&self) -> Frame {
+        self.published.read().get_frame()
+    }
+
+    pub fn latest_scan(&self) -> Scan {
+        self.published.read().scan()
+    }
+
+    // `frame` with the rover's movement while the points were measured undone, as seen from where
+    // it was at `reference`. `pose_at` looks up the odometry, without a pose for `reference` it's
+    // the plain frame.
+    pub fn deskewed_frame(
+        &self,
+        sectors: &[Sector],
+        reference: Instant,
+        pose_at: impl Fn(Instant) -> Option<Pose>,
+    ) -> Frame {
+        let snapshot = self.published.read();
+        match snapshot.deskew(reference, pose_at) {
+            Some(deskewed) => deskewed.frame(sectors),
+            None => snapshot.frame(sectors),
+        }
+    }
+
+    // for mapping, see `Scan::deskew`
+    pub fn deskewed_scan(
+        &self,
+        reference: Instant,
+        pose_at: impl Fn(Instant) -> Option<Pose>,
+    ) -> Option<Scan> {
+        self.latest_scan().deskew(reference, pose_at)
+    }
+
+    // records the raw packets until `stop_recording`, replaces a recording in progress
+    pub fn start_recording(&self, writer: impl Write + Send + 'static) -> std::io::Result<()> {
+        let recorder = Recorder::new(Box::new(writer) as Box<dyn Write + Send>)?;
+        if let Some(old) = self.recording.lock().unwrap().replace(recorder) {
+            old.into_inner()?;
+        }
+        Ok(())
+    }
+
+    // returns the writer, None if nothing was being recorded
+    pub fn stop_recording(&self) -> std::io::Result<Option<Box<dyn Write + Send>>> {
+        match self.recording.lock().unwrap().take() {
+            Some(recorder) => Ok(Some(recorder.into_inner()?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.lock().unwrap().is_some()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.published.read().stats()
+    }
+
+    // frames are arriving at the expected speed and density, false while the lidar is off
+    pub fn is_healthy(&self) -> bool {
+        self.published.read().is_healthy()
+    }
+
+    pub fn set_mask(&self, mask: Mask) {
+        self.data.lock().unwrap().set_mask(mask)
+    }
+
+    // Learns the self-occlusion mask from `scans` revolutions, run it with the lidar on and
+    // nothing but the rover within `max_distance`. Blocks until done, the new mask is set and
+    // returned so it can be put in the config.
+    pub fn calibrate_mask(&self, scans: u32, max_distance: u16, margin: u16) -> Mask {
+        let (resolution, max_age) = {
+            let mut data = self.data.lock().unwrap();
+            let Config {
+                resolution,
+                max_age,
+                ..
+            } = *data.config();
+            data.set_mask(Mask::new(resolution));
+            (resolution, max_age)
+        };
+        let mut calibration = Calibration::new(resolution, max_distance, margin);
+        // let the masked bins fill in
+        thread::sleep(max_age);
+        for _ in 0..scans {
+            calibration.add_scan(&self.latest_scan());
+            thread::sleep(REVOLUTION);
+        }
+        let mask = calibration.mask();
+        self.set_mask(mask.clone());
+        mask
+    }
+}
+
+impl RangeScanner for Lidar<'static> {
+    fn set_power(&mut self, value: bool) {
+        Lidar::set_power(self, value)
+    }
+
+    fn get_frame(&self, sectors: &[Sector]) -> Frame {
+        Lidar::frame(self, sectors)
+    }
+
+    fn power_status(&self) -> &PowerStatus {
+        Lidar::power_status(self)
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use hal::host::SimPin;
+    use hal::host::SimSerial;
+    use hal::host::SimSerialTx;
+
+    use super::*;
+    use crate::Packet;
+    use crate::ProtocolError;
+    use crate::Sample;
+
+    // a packet per byte, the byte is the angle in degrees
+    struct Requested {
+        resets: Arc<AtomicUsize>,
+    }
+
+    impl Protocol for Requested {
+        fn add_byte(&mut self, byte: u8) -> Result<Option<Packet>, ProtocolError> {
+            Ok(Some(Packet {
+                speed: 0,
+                samples: vec![Sample {
+                    angle: byte as u16 * 100,
+                    distance: 1000,
+                    intensity: 200,
+                }],
+            }))
+        }
+
+        fn reset(&mut self) {
+            self.resets.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn start_request(&self) -> &'static [u8] {
+            b"start"
+        }
+
+        fn stop_request(&self) -> &'static [u8] {
+            b"stop"
+        }
+    }
+
+    fn wait_for(mut condition: impl FnMut() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn requests_follow_the_power() {
+        let (serial_tx, serial) = SimSerial::channel();
+        let requests = SimSerialTx::new();
+        let power = SimPin::new();
+        let resets = Arc::new(AtomicUsize::new(0));
+        let protocol = Requested {
+            resets: resets.clone(),
+        };
+        let mut lidar = Lidar::with_requests(
+            serial,
+            requests.clone(),
+            power.clone(),
+            protocol,
+            Config::default(),
+        );
+        assert!(power.is_high());
+        thread::sleep(Duration::from_millis(150));
+        assert!(requests.take().is_empty());
+
+        // the reset comes before the request
+        let mut sent = Vec::new();
+        lidar.set_power_on();
+        assert!(!power.is_high());
+        assert!(wait_for(|| {
+            sent.extend(requests.take());
+            sent == b"start"
+        }));
+        assert_eq!(resets.load(Ordering::SeqCst), 1);
+
+        serial_tx.send(vec![10, 20, 30]).unwrap();
+        assert!(wait_for(|| lidar.stats().frames == 3));
+        assert_eq!(lidar.range_in(15.0, 25.0), Some(1000));
+
+        sent.clear();
+        lidar.set_power_off();
+        assert!(wait_for(|| {
+            sent.extend(requests.take());
+            sent == b"stop"
+        }));
+        assert_eq!(resets.load(Ordering::SeqCst), 2);
+    }
+}
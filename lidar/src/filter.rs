@@ -0,0 +1,78 @@
+// Filters for the ranges, listed in `Config::filters`. `Smooth` works across revolutions as points
+// are added, so wherever it is listed it sees them before the others. The others work on the bins
+// in the order listed, before sector statistics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    // clamps returns to `min`..=`max` mm
+    Range { min: u16, max: u16 },
+    // median of the returns within `neighbours` bins either side, bins without a return stay empty
+    Median { neighbours: usize },
+    // drops a return without another within `max_gap` mm in the `neighbours` bins either side, with
+    // no neighbours nothing is dropped
+    Isolated { neighbours: usize, max_gap: u16 },
+    // moves a bin's range `weight` (0..=1) of the way to each new return, a jump over `max_jump`
+    // mm is taken as is
+    Smooth { weight: f32, max_jump: u16 },
+}
+
+impl Filter {
+    // `previous` is the bin's range from the last revolution, other filters leave `distance` as is
+    pub(crate) fn smooth(&self, previous: Option<u16>, distance: u16) -> u16 {
+        match (self, previous) {
+            (Filter::Smooth { weight, max_jump }, Some(previous))
+                if previous.abs_diff(distance) <= *max_jump =>
+            {
+                (previous as f32 + weight * (distance as f32 - previous as f32)).round() as u16
+            }
+            _ => distance,
+        }
+    }
+
+    // the bins go all the way around, the first and last are neighbours
+    pub(crate) fn apply(&self, ranges: &[Option<u16>]) -> Vec<Option<u16>> {
+        // a window never wraps onto itself
+        let limit = |neighbours: usize| neighbours.min(ranges.len().saturating_sub(1) / 2);
+        match *self {
+            Filter::Range { min, max } => ranges
+                .iter()
+                .map(|range| range.map(|distance| distance.max(min).min(max)))
+                .collect(),
+            Filter::Median { neighbours } => (0..ranges.len())
+                .map(|i| {
+                    let neighbours = limit(neighbours);
+                    ranges[i]?;
+                    let mut window: Vec<u16> = around(ranges, i, neighbours).flatten().collect();
+                    window.sort_unstable();
+                    Some(window[window.len() / 2])
+                })
+                .collect(),
+            Filter::Isolated { neighbours: 0, .. } => ranges.to_vec(),
+            Filter::Isolated {
+                neighbours,
+                max_gap,
+            } => (0..ranges.len())
+                .map(|i| {
+                    let neighbours = limit(neighbours);
+                    let distance = ranges[i]?;
+                    around(ranges, i, neighbours)
+                        .enumerate()
+                        .filter(|(j, _)| *j != neighbours)
+                        .filter_map(|(_, range)| range)
+                        .any(|other| other.abs_diff(distance) <= max_gap)
+                        .then_some(distance)
+                })
+                .collect(),
+            Filter::Smooth { .. } => ranges.to_vec(),
+        }
+    }
+}
+
+// the bins from `neighbours` before `index` to `neighbours` after it
+fn around(
+    ranges: &[Option<u16>],
+    index: usize,
+    neighbours: usize,
+) -> impl Iterator<Item = Option<u16>> + '_ {
+    let len = ranges.len();
+    (0..=2 * neighbours).map(move |k| ranges[(index + len + k - neighbours) % len])
+}
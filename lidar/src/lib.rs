@@ -1,215 +1,334 @@
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
-use std::sync::Mutex;
-use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
-use embedded_hal::delay::DelayUs;
+mod deskew;
+mod driver;
+mod filter;
+mod mask;
+mod mount;
+mod power;
+mod protocol;
+mod publish;
+mod record;
+mod scan;
+mod sector;
+mod snapshot;
+mod speed;
+mod stats;
 
-use esp_idf_hal::delay::FreeRtos as delay;
-use esp_idf_hal::delay::TickType;
-use esp_idf_hal::gpio::AnyOutputPin;
-use esp_idf_hal::gpio::Output;
-use esp_idf_hal::gpio::PinDriver;
-use esp_idf_hal::uart::UartRxDriver;
-use lidar_ld19::LidarFrame;
-use log::warn;
+pub use deskew::Pose;
+pub use driver::Lidar;
+pub use filter::Filter;
+pub use mask::Calibration;
+pub use mask::Mask;
+pub use mask::MaskZone;
+pub use mount::Mount;
+pub use mount::Point;
+pub use power::PowerState;
+pub use power::PowerStatus;
+pub use protocol::Ld06;
+pub use protocol::Ld19;
+pub use protocol::Ld19Parser;
+pub use protocol::LidarFrame;
+pub use protocol::LidarPoint;
+pub use protocol::Packet;
+pub use protocol::Protocol;
+pub use protocol::ProtocolError;
+pub use protocol::RplidarA1;
+pub use protocol::Sample;
+pub use publish::Published;
+pub use record::Record;
+pub use record::Recorder;
+pub use record::Replay;
+pub use scan::Scan;
+pub use scan::ScanPoint;
+pub use sector::Sector;
+pub use sector::Statistic;
+pub use sector::DEFAULT_SECTORS;
+pub use snapshot::Snapshot;
+pub use speed::SpeedConfig;
+pub use stats::Health;
+pub use stats::Stats;
 
-const SAMPLE_COUNT: usize = 360;
+use speed::SpeedControl;
 
-pub struct Lidar<'a> {
-    power: PinDriver<'a, AnyOutputPin, Output>,
-    data: Arc<Mutex<Data>>,
-    running: Arc<AtomicBool>,
-    synced: Arc<AtomicBool>,
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub resolution: f32,            // degrees per bin
+    pub min_confidence: u8,         // points with a lower confidence are invalid
+    pub max_age: Duration,          // bins not refreshed for longer are ignored
+    pub sectors: &'static [Sector], // reported by `get_frame`
+    pub mount: Mount,
+    pub mask: &'static [MaskZone], // returns from the rover itself
+    pub health: Health,
+    pub speed: Option<SpeedConfig>, // with a PWM speed input, the LD19's default if None
+    pub filters: &'static [Filter], // applied before the sector statistics, see `Filter`
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Config {
+    // a base for const configs, `..lidar::Config::DEFAULT`
+    pub const DEFAULT: Self = Self {
+        resolution: 1.0,
+        min_confidence: 100,
+        max_age: Duration::from_millis(500),
+        sectors: DEFAULT_SECTORS,
+        mount: Mount::CENTERED,
+        mask: &[],
+        health: Health::LD19,
+        speed: None,
+        filters: &[],
+    };
+}
+
+// Accumulates the points, each bin holds the latest valid point, None when the bin was never seen
+// or its latest point was invalid (no return, low confidence or masked). Bins are by angle from
+// the rover's front, the sensor's angles are turned by the mount as points are added.
 #[derive(Debug)]
-struct Data {
-    samples: [u16; SAMPLE_COUNT],
+pub struct Data {
+    mask: Mask,
+    snapshot: Snapshot,
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
 }
 
 impl Data {
-    fn new() -> Self {
+    pub fn new(config: Config) -> Self {
         Self {
-            samples: [0u16; SAMPLE_COUNT],
+            mask: Mask::from_zones(config.resolution, config.mask),
+            snapshot: Snapshot::new(config),
         }
     }
 
-    fn update(&mut self, frame: LidarFrame) {
-        for point in frame.points {
-            let index = match point.angle as usize / 100 {
-                x if x >= self.samples.len() => self.samples.len() - 1,
-                x => x,
-            };
-            self.samples[index] = point.distance;
+    // `received` is when the last byte of the packet arrived, earlier samples are timed back from
+    // it at the rotation speed. Returns true when the packet started a new revolution.
+    pub fn update(&mut self, packet: &Packet, received: Instant) -> bool {
+        let (Some(first), Some(last)) = (packet.samples.first(), packet.samples.last()) else {
+            return false;
+        };
+        let count = packet.samples.len();
+        let monitor = &mut self.snapshot.monitor;
+        let revolution = monitor.frame(packet.speed, first.angle, count as u32, received);
+        let span = (last.angle as u32 + 36000 - first.angle as u32) % 36000;
+        // measured by the monitor when the sensor doesn't report it
+        let step = match monitor.speed {
+            0 => Duration::ZERO,
+            _ if count < 2 => Duration::ZERO,
+            speed => {
+                Duration::from_secs_f32(span as f32 / 100.0 / (count - 1) as f32 / speed as f32)
+            }
+        };
+        for (i, sample) in packet.samples.iter().enumerate() {
+            self.add_point(ScanPoint {
+                angle: sample.angle as f32 / 100.0,
+                distance: sample.distance,
+                intensity: sample.intensity,
+                time: received - step * (count - 1 - i) as u32,
+            });
         }
+        revolution
     }
 
-    pub fn reset(&mut self) {
-        for i in &mut self.samples {
-            *i = 0;
+    // `point.angle` is as the sensor reports it
+    pub fn add_point(&mut self, mut point: ScanPoint) {
+        point.angle = self.snapshot.config.mount.angle(point.angle);
+        let index = self.snapshot.bin(point.angle);
+        let config = &self.snapshot.config;
+        let bins = &mut self.snapshot.bins;
+        let valid = point.distance > 0
+            && point.intensity >= config.min_confidence
+            && !self.mask.masks(point.angle, point.distance);
+        if valid {
+            // smoothed against the bin's point from the last revolution
+            let previous = bins[index]
+                .filter(|p| point.time.saturating_duration_since(p.time) <= config.max_age)
+                .map(|p| p.distance);
+            for filter in config.filters {
+                point.distance = filter.smooth(previous, point.distance);
+            }
         }
+        bins[index] = valid.then_some(point);
     }
 
-    pub fn get_range_left(&self) -> u16 {
-        let mut value = 12000u16;
-        for dist in &self.samples[224..315] {
-            if *dist < value {
-                value = *dist;
-            }
-        }
-        value
+    pub fn set_mask(&mut self, mask: Mask) {
+        self.mask = mask;
     }
 
-    pub fn get_range_front(&self) -> u16 {
-        let mut value = 12000u16;
-        for dist in &self.samples[315..=359] {
-            if *dist < value {
-                value = *dist;
-            }
-        }
-        for dist in &self.samples[0..45] {
-            if *dist < value {
-                value = *dist;
-            }
-        }
-        value
+    pub fn mask(&self) -> &Mask {
+        &self.mask
     }
 
-    pub fn get_range_right(&self) -> u16 {
-        let mut value = 12000u16;
-        for dist in &self.samples[45..135] {
-            if *dist < value {
-                value = *dist;
-            }
-        }
-        value
+    pub fn reset(&mut self) {
+        self.snapshot = Snapshot::new(self.snapshot.config);
+    }
+
+    // a frame that failed to parse
+    pub fn add_error(&mut self) {
+        self.snapshot.monitor.error();
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.snapshot.config
+    }
+
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.snapshot.stats()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.snapshot.is_healthy()
+    }
+
+    pub fn scan(&self) -> Scan {
+        self.snapshot.scan()
+    }
+
+    pub fn range_in(&self, start: f32, end: f32) -> Option<u16> {
+        self.snapshot.range_in(start, end)
+    }
+
+    pub fn sector_range(&self, sector: &Sector) -> Option<u16> {
+        self.snapshot.sector_range(sector)
+    }
+
+    pub fn frame(&self, sectors: &[Sector]) -> Frame {
+        self.snapshot.frame(sectors)
     }
 
     pub fn get_frame(&self) -> Frame {
-        Frame {
-            range_left: self.get_range_left(),
-            range_front: self.get_range_front(),
-            range_right: self.get_range_right(),
-        }
+        self.snapshot.get_frame()
     }
 }
 
-#[derive(Debug)]
+// What the brain needs from a range sensor, `Lidar` with any of the protocols, the simulator
+// provides its own.
+pub trait RangeScanner: Send + 'static {
+    fn set_power(&mut self, value: bool);
+    fn get_frame(&self, sectors: &[Sector]) -> Frame;
+    fn power_status(&self) -> &PowerStatus;
+}
+
+// Range in mm for each requested sector, None when the sector has no data.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Frame {
-    range_left: u16,
-    range_front: u16,
-    range_right: u16,
+    ranges: Vec<(&'static str, Option<u16>)>,
 }
 
 impl Frame {
-    pub fn get_range_left(&self) -> u32 {
-        self.range_left as u32
+    pub fn new(ranges: Vec<(&'static str, Option<u16>)>) -> Self {
+        Self { ranges }
     }
 
-    pub fn get_range_front(&self) -> u32 {
-        self.range_front as u32
+    // None when the sector has no data or wasn't requested
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.ranges
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, range)| range.map(u32::from))
     }
 
-    pub fn get_range_right(&self) -> u32 {
-        self.range_right as u32
+    pub fn ranges(&self) -> &[(&'static str, Option<u16>)] {
+        &self.ranges
     }
-}
 
-impl<'a> Lidar<'a> {
-    pub fn new(serial: UartRxDriver<'static>, power: AnyOutputPin) -> Self {
-        let data = Arc::new(Mutex::new(Data::new()));
-        let running = Arc::new(AtomicBool::new(false));
-        let synced = Arc::new(AtomicBool::new(false));
-
-        {
-            let data = data.clone();
-            let running = running.clone();
-            let synced = synced.clone();
-            _ = thread::Builder::new()
-                .stack_size(8192)
-                .name("lidar".into())
-                .spawn(move || {
-                    let mut ld19 = lidar_ld19::LidarLD19::new();
-                    loop {
-                        if !running.load(Ordering::SeqCst) {
-                            synced.store(false, Ordering::SeqCst);
-                            data.lock().unwrap().reset();
-                            // wait for lidar to be enabled
-                            while !running.load(Ordering::SeqCst) {
-                                delay.delay_ms(100);
-                            }
-                            // delay for lidar to get up to speed
-                            delay.delay_ms(6000);
-                        }
-                        let mut buffer = [0u8];
-                        serial
-                            .read(&mut buffer, TickType::from(Duration::from_millis(100)).0)
-                            .expect("uart read failed!");
-                        match ld19.add_byte(buffer[0]) {
-                            Err(err) => warn!("lidar error: {err:?}"),
-                            Ok(Some(frame)) => {
-                                if !synced.load(Ordering::SeqCst) {
-                                    synced.store(true, Ordering::SeqCst);
-                                }
-                                data.lock().unwrap().update(frame);
-                            }
-                            Ok(None) => {}
-                        }
-                    }
-                })
-                .unwrap();
-        }
-
-        // TODO: how can I set this high before making it an output.
-        let mut power = PinDriver::output_od(power).unwrap();
-        power.set_high().unwrap();
-
-        Lidar {
-            power,
-            data,
-            running,
-            synced,
-        }
+    pub fn get_range_left(&self) -> Option<u32> {
+        self.get("left")
     }
 
-    pub fn set_power_on(&mut self) {
-        self.power.set_low().unwrap();
-        self.running.store(true, Ordering::SeqCst)
+    pub fn get_range_front(&self) -> Option<u32> {
+        self.get("front")
     }
 
-    pub fn set_power_off(&mut self) {
-        self.power.set_high().unwrap();
-        self.running.store(false, Ordering::SeqCst)
+    pub fn get_range_right(&self) -> Option<u32> {
+        self.get("right")
     }
+}
 
-    pub fn set_power(&mut self, value: bool) {
-        if value {
-            self.set_power_on()
-        } else {
-            self.set_power_off()
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn is_synced(&self) -> bool {
-        self.synced.load(Ordering::SeqCst)
+    const SMOOTH: Filter = Filter::Smooth {
+        weight: 0.5,
+        max_jump: 200,
+    };
+
+    fn point(distance: u16, time: Instant) -> ScanPoint {
+        ScanPoint {
+            angle: 10.0,
+            distance,
+            intensity: 200,
+            time,
+        }
     }
 
-    pub fn get_range_left(&self) -> u16 {
-        self.data.lock().unwrap().get_range_left()
+    #[test]
+    fn smooth_then_the_bin_filters() {
+        let mut data = Data::new(Config {
+            filters: &[SMOOTH, Filter::Range { min: 0, max: 1060 }],
+            ..Config::DEFAULT
+        });
+        let now = Instant::now();
+        data.add_point(point(1000, now));
+        // smoothed to 1050, under the clamp
+        data.add_point(point(1100, now));
+        assert_eq!(data.range_in(9.5, 10.5), Some(1050));
     }
 
-    pub fn get_range_front(&self) -> u16 {
-        self.data.lock().unwrap().get_range_front()
+    #[test]
+    fn smooth_listed_after_another_filter() {
+        let mut data = Data::new(Config {
+            filters: &[Filter::Median { neighbours: 1 }, SMOOTH],
+            ..Config::DEFAULT
+        });
+        let now = Instant::now();
+        data.add_point(point(1000, now));
+        data.add_point(point(1100, now));
+        assert_eq!(data.range_in(9.5, 10.5), Some(1050));
     }
 
-    pub fn get_range_right(&self) -> u16 {
-        self.data.lock().unwrap().get_range_right()
+    #[test]
+    fn range_clamps() {
+        let mut data = Data::new(Config {
+            filters: &[Filter::Range {
+                min: 100,
+                max: 1050,
+            }],
+            ..Config::DEFAULT
+        });
+        let now = Instant::now();
+        data.add_point(point(1100, now));
+        data.add_point(ScanPoint {
+            angle: 20.0,
+            ..point(50, now)
+        });
+        assert_eq!(data.range_in(9.5, 10.5), Some(1050));
+        assert_eq!(data.range_in(19.5, 20.5), Some(100));
     }
 
-    pub fn get_frame(&self) -> Frame {
-        self.data.lock().unwrap().get_frame()
+    #[test]
+    fn isolated_without_neighbours_keeps_everything() {
+        let mut data = Data::new(Config {
+            filters: &[Filter::Isolated {
+                neighbours: 0,
+                max_gap: 100,
+            }],
+            ..Config::DEFAULT
+        });
+        data.add_point(point(1000, Instant::now()));
+        assert_eq!(data.range_in(9.5, 10.5), Some(1000));
     }
 }
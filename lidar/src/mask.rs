@@ -0,0 +1,105 @@
+use crate::sector;
+use crate::Scan;
+
+// Points in start..end degrees from the rover's front that are no further than max_distance hit
+// the rover itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaskZone {
+    pub start: f32,
+    pub end: f32,
+    pub max_distance: u16, // mm
+}
+
+// Self-occlusion mask, the furthest distance in each bin that is still the rover's body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    resolution: f32,
+    limits: Vec<u16>,
+}
+
+impl Mask {
+    pub fn new(resolution: f32) -> Self {
+        let count = (360.0 / resolution).ceil() as usize;
+        Self {
+            resolution,
+            limits: vec![0; count],
+        }
+    }
+
+    pub fn from_zones(resolution: f32, zones: &[MaskZone]) -> Self {
+        let mut mask = Self::new(resolution);
+        for (i, limit) in mask.limits.iter_mut().enumerate() {
+            let angle = i as f32 * resolution;
+            *limit = zones
+                .iter()
+                .filter(|z| sector::contains(z.start, z.end, angle))
+                .map(|z| z.max_distance)
+                .max()
+                .unwrap_or(0);
+        }
+        mask
+    }
+
+    fn index(&self, angle: f32) -> usize {
+        ((angle / self.resolution) as usize).min(self.limits.len() - 1)
+    }
+
+    // `angle` is from the rover's front
+    pub fn masks(&self, angle: f32, distance: u16) -> bool {
+        distance <= self.limits[self.index(angle)]
+    }
+
+    pub fn limits(&self) -> &[u16] {
+        &self.limits
+    }
+}
+
+// Learns the mask from scans taken with nothing but the rover within `max_distance`, bins that
+// see a return that close in at least half of the scans are masked out to the furthest one plus
+// `margin`.
+#[derive(Debug)]
+pub struct Calibration {
+    max_distance: u16,
+    margin: u16,
+    scans: u32,
+    hits: Vec<u32>,
+    mask: Mask,
+}
+
+impl Calibration {
+    pub fn new(resolution: f32, max_distance: u16, margin: u16) -> Self {
+        let mask = Mask::new(resolution);
+        Self {
+            max_distance,
+            margin,
+            scans: 0,
+            hits: vec![0; mask.limits.len()],
+            mask,
+        }
+    }
+
+    // the scan should be taken without a mask
+    pub fn add_scan(&mut self, scan: &Scan) {
+        self.scans += 1;
+        for point in scan.points() {
+            if point.distance > self.max_distance {
+                continue;
+            }
+            let index = self.mask.index(point.angle);
+            self.hits[index] += 1;
+            let limit = &mut self.mask.limits[index];
+            *limit = (*limit).max(point.distance);
+        }
+    }
+
+    pub fn mask(&self) -> Mask {
+        let mut mask = self.mask.clone();
+        for (limit, hits) in mask.limits.iter_mut().zip(&self.hits) {
+            *limit = match hits * 2 >= self.scans && *hits > 0 {
+                true => limit.saturating_add(self.margin),
+                false => 0,
+            };
+        }
+        mask
+    }
+}
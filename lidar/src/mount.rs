@@ -0,0 +1,57 @@
+// Where the sensor sits on the chassis. The base frame has x forward and y to the left of the
+// rover's center, like the odometry pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mount {
+    pub x: f32,          // mm
+    pub y: f32,          // mm
+    pub yaw: f32,        // degrees clockwise from the rover's front to the sensor's 0
+    pub clockwise: bool, // the sensor's angles increase clockwise, the LD19 does
+}
+
+impl Mount {
+    // at the center, 0 to the front, clockwise
+    pub const CENTERED: Mount = Mount {
+        x: 0.0,
+        y: 0.0,
+        yaw: 0.0,
+        clockwise: true,
+    };
+
+    // a sensor angle as degrees clockwise from the rover's front, 0..360
+    pub fn angle(&self, sensor_angle: f32) -> f32 {
+        let angle = match self.clockwise {
+            true => self.yaw + sensor_angle,
+            false => self.yaw - sensor_angle,
+        };
+        angle.rem_euclid(360.0)
+    }
+
+    // a range at `angle` degrees clockwise from the rover's front, measured from the sensor
+    pub fn point(&self, angle: f32, distance: u16) -> Point {
+        let (sin, cos) = (-angle.to_radians()).sin_cos();
+        Point {
+            x: self.x + distance as f32 * cos,
+            y: self.y + distance as f32 * sin,
+        }
+    }
+
+    // a base frame point as degrees clockwise from the rover's front and mm from the sensor
+    pub fn polar(&self, point: Point) -> (f32, u16) {
+        let (x, y) = (point.x - self.x, point.y - self.y);
+        let angle = (-y.atan2(x)).to_degrees().rem_euclid(360.0);
+        (angle, x.hypot(y).round().min(u16::MAX as f32) as u16)
+    }
+}
+
+impl Default for Mount {
+    fn default() -> Self {
+        Self::CENTERED
+    }
+}
+
+// Cartesian point in the base frame, mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
@@ -0,0 +1,126 @@
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+use hal::Callbacks;
+use log::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    Off,
+    SpinningUp, // powered, not up to speed or no full revolution yet
+    Synced,     // healthy, the ranges can be used
+    Degraded,   // was synced, stalled or slowed down since
+}
+
+impl PowerState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => PowerState::SpinningUp,
+            2 => PowerState::Synced,
+            3 => PowerState::Degraded,
+            _ => PowerState::Off,
+        }
+    }
+}
+
+// Power state machine, whoever owns the sensor reports power changes and the health after new
+// data, the callbacks run on changes. Clones share the state and callbacks.
+#[derive(Clone, Default)]
+pub struct PowerStatus {
+    state: Arc<AtomicU8>,
+    callbacks: Callbacks<PowerState>,
+}
+
+impl PowerStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> PowerState {
+        PowerState::from_u8(self.state.load(Ordering::SeqCst))
+    }
+
+    // the callback runs on the thread that reports the change, keep it short
+    pub fn on_change(&self, callback: impl Fn(PowerState) + Send + Sync + 'static) {
+        self.callbacks.add(callback);
+    }
+
+    // changes are sent for as long as the receiver is kept
+    pub fn subscribe(&self) -> Receiver<PowerState> {
+        self.callbacks.subscribe()
+    }
+
+    pub fn power(&self, on: bool) {
+        self.set(match on {
+            true => PowerState::SpinningUp,
+            false => PowerState::Off,
+        });
+    }
+
+    pub fn update(&self, healthy: bool) {
+        match (self.state(), healthy) {
+            (PowerState::SpinningUp | PowerState::Degraded, true) => self.set(PowerState::Synced),
+            (PowerState::Synced, false) => self.set(PowerState::Degraded),
+            _ => {}
+        }
+    }
+
+    fn set(&self, state: PowerState) {
+        let old = PowerState::from_u8(self.state.swap(state as u8, Ordering::SeqCst));
+        if old != state {
+            info!("lidar {old:?} -> {state:?}");
+            self.callbacks.call(state);
+        }
+    }
+}
+
+impl std::fmt::Debug for PowerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PowerStatus")
+            .field("state", &self.state())
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_health() {
+        let status = PowerStatus::new();
+        let changes = status.subscribe();
+        status.power(true);
+        status.update(false);
+        status.update(true);
+        status.update(false);
+        status.update(true);
+        status.power(false);
+        let changes: Vec<_> = changes.try_iter().collect();
+        use PowerState::*;
+        assert_eq!(changes, [SpinningUp, Synced, Degraded, Synced, Off]);
+    }
+
+    #[test]
+    fn callbacks_can_use_the_status() {
+        let status = PowerStatus::new();
+        let changes = status.subscribe();
+        {
+            let owner = status.clone();
+            status.on_change(move |state| {
+                // powering off from the callback reports the change again
+                if state == PowerState::Degraded {
+                    owner.power(false);
+                }
+            });
+        }
+        status.power(true);
+        status.update(true);
+        status.update(false);
+        assert_eq!(status.state(), PowerState::Off);
+        assert_eq!(changes.try_iter().last(), Some(PowerState::Off));
+    }
+}
@@ -0,0 +1,123 @@
+mod ld19;
+mod rplidar;
+
+pub use ld19::Ld19;
+pub use lidar_ld19::Ld19Parser;
+pub use lidar_ld19::LidarFrame;
+pub use lidar_ld19::LidarPoint;
+pub use rplidar::RplidarA1;
+
+// same packets and baud rate as the LD19
+pub type Ld06 = Ld19;
+
+// Samples the sensor sent together, LD19/LD06 send 12 at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub speed: u16, // degrees per second, 0 when the sensor doesn't report it
+    pub samples: Vec<Sample>,
+}
+
+// Angle as the sensor measures it, clockwise with 0 to the sensor's front.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub angle: u16,    // centidegrees, 0..36000
+    pub distance: u16, // mm, 0 for no return
+    pub intensity: u8, // 0..=255, the LD19's confidence
+}
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    Crc,     // a packet failed its check
+    Invalid, // bytes that can't be part of a packet, the parser resyncs
+}
+
+// Serial protocol of a lidar, the receive thread feeds it every byte it reads.
+pub trait Protocol: Send {
+    fn add_byte(&mut self, byte: u8) -> Result<Option<Packet>, ProtocolError>;
+
+    // drops whatever was partly read, the sensor was powered on or off
+    fn reset(&mut self);
+
+    // sent to sensors that only stream on request, after power on and before power off
+    fn start_request(&self) -> &'static [u8] {
+        &[]
+    }
+
+    fn stop_request(&self) -> &'static [u8] {
+        &[]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD06 packet from the LDROBOT manual
+    const LD06_PACKET: [u8; 47] = [
+        0x54, 0x2c, 0x68, 0x08, 0xab, 0x7e, 0xe0, 0x00, 0xe4, 0xdc, 0x00, 0xe2, 0xd9, 0x00, 0xe5,
+        0xd5, 0x00, 0xe3, 0xd3, 0x00, 0xe4, 0xd0, 0x00, 0xe9, 0xcd, 0x00, 0xe4, 0xca, 0x00, 0xe2,
+        0xc7, 0x00, 0xe9, 0xc5, 0x00, 0xe5, 0xc2, 0x00, 0xe5, 0xc0, 0x00, 0xe5, 0xbe, 0x82, 0x3a,
+        0x1a, 0x50,
+    ];
+
+    fn feed(protocol: &mut impl Protocol, bytes: &[u8]) -> (Vec<Packet>, usize) {
+        let mut packets = Vec::new();
+        let mut errors = 0;
+        for byte in bytes {
+            match protocol.add_byte(*byte) {
+                Ok(Some(packet)) => packets.push(packet),
+                Ok(None) => {}
+                Err(_) => errors += 1,
+            }
+        }
+        (packets, errors)
+    }
+
+    #[test]
+    fn ld06_packet() {
+        let mut ld06 = Ld06::new();
+        let stream: Vec<u8> = [0x00, 0x54, 0x12]
+            .iter()
+            .chain(&LD06_PACKET)
+            .copied()
+            .collect();
+        let (packets, _) = feed(&mut ld06, &stream);
+        assert_eq!(packets.len(), 1);
+        let packet = &packets[0];
+        assert_eq!(packet.speed, 2152);
+        assert_eq!(packet.samples.len(), 12);
+        assert_eq!(
+            packet.samples[0],
+            Sample {
+                angle: 32427,
+                distance: 224,
+                intensity: 0xe4,
+            }
+        );
+        assert_eq!(packet.samples[11].angle, 33470);
+        assert_eq!(packet.samples[11].distance, 192);
+        let distances: Vec<u16> = packet.samples.iter().map(|s| s.distance).collect();
+        assert_eq!(
+            distances,
+            [224, 220, 217, 213, 211, 208, 205, 202, 199, 197, 194, 192]
+        );
+    }
+
+    #[test]
+    fn ld06_reset_drops_a_partial_packet() {
+        let mut ld06 = Ld06::new();
+        let (packets, _) = feed(&mut ld06, &LD06_PACKET[..20]);
+        assert!(packets.is_empty());
+        ld06.reset();
+        let (packets, errors) = feed(&mut ld06, &LD06_PACKET);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(errors, 0);
+    }
+
+    #[test]
+    fn ld06_sends_no_requests() {
+        let ld06 = Ld06::new();
+        assert!(ld06.start_request().is_empty());
+        assert!(ld06.stop_request().is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use lidar_ld19::Error;
+use lidar_ld19::Ld19Parser;
+use lidar_ld19::LidarFrame;
+
+use super::Packet;
+use super::Protocol;
+use super::ProtocolError;
+use super::Sample;
+
+// The packet parser is in lidar-ld19, this adapts it to the driver.
+pub struct Ld19 {
+    parser: Ld19Parser,
+}
+
+impl Ld19 {
+    pub fn new() -> Self {
+        Self {
+            parser: Ld19Parser::new(),
+        }
+    }
+}
+
+impl Default for Ld19 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Protocol for Ld19 {
+    fn add_byte(&mut self, byte: u8) -> Result<Option<Packet>, ProtocolError> {
+        Ok(self
+            .parser
+            .add_byte(byte)?
+            .map(|frame| Packet::from(&frame)))
+    }
+
+    fn reset(&mut self) {
+        self.parser.reset();
+    }
+}
+
+impl From<Error> for ProtocolError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Crc => ProtocolError::Crc,
+            Error::Invalid => ProtocolError::Invalid,
+        }
+    }
+}
+
+impl From<&LidarFrame> for Packet {
+    fn from(frame: &LidarFrame) -> Self {
+        Packet {
+            speed: frame.speed,
+            samples: frame
+                .points
+                .iter()
+                .map(|point| Sample {
+                    angle: point.angle,
+                    distance: point.distance,
+                    intensity: point.confidence,
+                })
+                .collect(),
+        }
+    }
+}
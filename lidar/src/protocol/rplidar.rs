@@ -0,0 +1,211 @@
+use super::Packet;
+use super::Protocol;
+use super::ProtocolError;
+use super::Sample;
+
+// Response descriptor sent once after SCAN, then 5 byte nodes:
+//   quality:6 !S:1 S:1 | angle_q6[6:0] C:1 | angle_q6[14:7] | distance_q2:u16
+// S marks the first node of a revolution and C is always 1.
+const DESCRIPTOR: [u8; 7] = [0xa5, 0x5a, 0x05, 0x00, 0x00, 0x40, 0x81];
+const NODE_SIZE: usize = 5;
+// nodes per packet, the same as an LD19 packet
+const POINTS: usize = 12;
+
+// RPLidar A1, 115200 baud. The sensor only streams after a SCAN request, build the lidar with
+// `Lidar::with_requests` to have SCAN and STOP sent as it's powered on and off. The motor runs
+// from the MOTOCTL pin, which takes the place of the LD19 power pin. It spins at about 5.5Hz, use
+// `Health::RPLIDAR_A1`.
+pub struct RplidarA1 {
+    synced: usize, // descriptor bytes matched
+    node: Vec<u8>,
+    samples: Vec<Sample>,
+}
+
+impl RplidarA1 {
+    pub const SCAN: [u8; 2] = [0xa5, 0x20];
+    pub const STOP: [u8; 2] = [0xa5, 0x25];
+
+    pub fn new() -> Self {
+        Self {
+            synced: 0,
+            node: Vec::with_capacity(NODE_SIZE),
+            samples: Vec::with_capacity(POINTS),
+        }
+    }
+
+    fn take_packet(&mut self) -> Option<Packet> {
+        match self.samples.is_empty() {
+            true => None,
+            false => Some(Packet {
+                speed: 0,
+                samples: std::mem::replace(&mut self.samples, Vec::with_capacity(POINTS)),
+            }),
+        }
+    }
+}
+
+impl Default for RplidarA1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Protocol for RplidarA1 {
+    fn add_byte(&mut self, byte: u8) -> Result<Option<Packet>, ProtocolError> {
+        if self.synced < DESCRIPTOR.len() {
+            self.synced = match byte {
+                _ if byte == DESCRIPTOR[self.synced] => self.synced + 1,
+                _ if byte == DESCRIPTOR[0] => 1,
+                _ => 0,
+            };
+            return Ok(None);
+        }
+        self.node.push(byte);
+        if self.node.len() < NODE_SIZE {
+            return Ok(None);
+        }
+        let node = &self.node;
+        let start = node[0] & 1 == 1;
+        let inverse = node[0] & 2 == 2;
+        if start == inverse || node[1] & 1 == 0 {
+            // lost the node boundaries, slide along a byte at a time until they check again
+            self.node.remove(0);
+            return Err(ProtocolError::Invalid);
+        }
+        let angle_q6 = (node[2] as u32) << 7 | (node[1] as u32) >> 1;
+        let sample = Sample {
+            angle: ((angle_q6 * 100 / 64) % 36000) as u16,
+            distance: u16::from_le_bytes([node[3], node[4]]) / 4,
+            // the 6 bit quality scaled to the LD19's range, good returns are around 190
+            intensity: (node[0] >> 2) << 2,
+        };
+        self.node.clear();
+        // a packet doesn't span two revolutions
+        let packet = match start {
+            true => self.take_packet(),
+            false => None,
+        };
+        self.samples.push(sample);
+        match packet {
+            Some(packet) => Ok(Some(packet)),
+            None if self.samples.len() == POINTS => Ok(self.take_packet()),
+            None => Ok(None),
+        }
+    }
+
+    // a new SCAN starts with the descriptor again
+    fn reset(&mut self) {
+        self.synced = 0;
+        self.node.clear();
+        self.samples.clear();
+    }
+
+    fn start_request(&self) -> &'static [u8] {
+        &Self::SCAN
+    }
+
+    fn stop_request(&self) -> &'static [u8] {
+        &Self::STOP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a node as the protocol document lays it out, `degrees` and `distance` in mm
+    fn node(start: bool, quality: u8, degrees: f32, distance: u16) -> [u8; NODE_SIZE] {
+        let angle_q6 = (degrees * 64.0) as u16;
+        let [d0, d1] = (distance * 4).to_le_bytes();
+        [
+            quality << 2 | (!start as u8) << 1 | start as u8,
+            (angle_q6 as u8) << 1 | 1,
+            (angle_q6 >> 7) as u8,
+            d0,
+            d1,
+        ]
+    }
+
+    // the end of one revolution and 13 nodes of the next, 1 degree apart
+    fn stream() -> Vec<u8> {
+        let mut stream = DESCRIPTOR.to_vec();
+        stream.extend(node(false, 47, 358.0, 900));
+        stream.extend(node(false, 47, 359.0, 950));
+        for i in 0..13 {
+            stream.extend(node(i == 0, 47, 0.5 + i as f32, 1000 + i as u16));
+        }
+        stream
+    }
+
+    fn feed(a1: &mut RplidarA1, bytes: &[u8]) -> (Vec<Packet>, usize) {
+        let mut packets = Vec::new();
+        let mut errors = 0;
+        for byte in bytes {
+            match a1.add_byte(*byte) {
+                Ok(Some(packet)) => packets.push(packet),
+                Ok(None) => {}
+                Err(_) => errors += 1,
+            }
+        }
+        (packets, errors)
+    }
+
+    #[test]
+    fn revolutions_split_packets() {
+        let mut a1 = RplidarA1::new();
+        // the SCAN response comes after whatever was on the line
+        let mut bytes = vec![0x00, 0xa5, 0x12];
+        bytes.extend(stream());
+        let (packets, errors) = feed(&mut a1, &bytes);
+        assert_eq!(errors, 0);
+        assert_eq!(packets.len(), 2);
+        let angles: Vec<u16> = packets[0].samples.iter().map(|s| s.angle).collect();
+        assert_eq!(angles, [35800, 35900]);
+        let first = packets[1].samples[0];
+        assert_eq!(
+            first,
+            Sample {
+                angle: 50,
+                distance: 1000,
+                intensity: 188,
+            }
+        );
+        assert_eq!(packets[1].samples.len(), POINTS);
+        assert_eq!(packets[1].samples[11].angle, 1150);
+        assert_eq!(packets[1].speed, 0);
+    }
+
+    #[test]
+    fn resyncs_on_a_stray_byte() {
+        let mut a1 = RplidarA1::new();
+        let stream = stream();
+        let mut bytes = stream[..DESCRIPTOR.len()].to_vec();
+        bytes.push(0x00);
+        bytes.extend(&stream[DESCRIPTOR.len()..]);
+        let (packets, errors) = feed(&mut a1, &bytes);
+        assert_eq!(errors, 1);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[1].samples.len(), POINTS);
+    }
+
+    #[test]
+    fn reset_waits_for_the_descriptor() {
+        let mut a1 = RplidarA1::new();
+        let stream = stream();
+        feed(&mut a1, &stream[..DESCRIPTOR.len() + 7]);
+        a1.reset();
+        // the nodes without the SCAN response before them
+        let (packets, _) = feed(&mut a1, &stream[DESCRIPTOR.len()..]);
+        assert!(packets.is_empty());
+        let (packets, errors) = feed(&mut a1, &stream);
+        assert_eq!(errors, 0);
+        assert_eq!(packets.len(), 2);
+    }
+
+    #[test]
+    fn requests() {
+        let a1 = RplidarA1::new();
+        assert_eq!(a1.start_request(), [0xa5, 0x20]);
+        assert_eq!(a1.stop_request(), [0xa5, 0x25]);
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::atomic::fence;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::stats::Monitor;
+use crate::Config;
+use crate::ScanPoint;
+use crate::Snapshot;
+
+// Words for the monitor ahead of the bins, two words per bin.
+const MONITOR_WORDS: usize = 3;
+
+// Read attempts that spin before yielding, and yield before sleeping. On a single core a spinning
+// reader only lets a writer of the same or lower priority finish by giving up the CPU.
+const SPINS: u32 = 8;
+const YIELDS: u32 = 16;
+
+// Double-buffered seqlock the receive thread publishes snapshots through. The writer fills the
+// buffer readers aren't pointed at and then flips `current`, so it never waits, and readers only
+// retry when the writer got all the way around to their buffer while they copied it. Only one
+// thread may publish, other sensors that fill a `Data` publish through it too.
+#[derive(Debug)]
+pub struct Published {
+    config: Config,
+    base: Instant, // times are stored as microseconds since
+    current: AtomicUsize,
+    buffers: [Buffer; 2],
+}
+
+#[derive(Debug)]
+struct Buffer {
+    sequence: AtomicU32, // odd while being written
+    words: Vec<AtomicU64>,
+}
+
+impl Buffer {
+    fn new(bins: usize) -> Self {
+        Self {
+            sequence: AtomicU32::new(0),
+            words: (0..MONITOR_WORDS + bins * 2)
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+        }
+    }
+}
+
+impl Published {
+    pub fn new(config: Config) -> Self {
+        let bins = Snapshot::new(config).bins.len();
+        Self {
+            config,
+            base: Instant::now(),
+            current: AtomicUsize::new(0),
+            buffers: [Buffer::new(bins), Buffer::new(bins)],
+        }
+    }
+
+    fn micros(&self, time: Instant) -> u64 {
+        time.saturating_duration_since(self.base).as_micros() as u64
+    }
+
+    fn time(&self, micros: u64) -> Instant {
+        self.base + Duration::from_micros(micros)
+    }
+
+    pub fn publish(&self, snapshot: &Snapshot) {
+        let next = 1 - self.current.load(Ordering::Relaxed);
+        let buffer = &self.buffers[next];
+        let sequence = buffer.sequence.load(Ordering::Relaxed);
+        buffer
+            .sequence
+            .store(sequence.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        let monitor = &snapshot.monitor;
+        let words = [
+            (monitor.frames as u64) << 32 | monitor.errors as u64,
+            (monitor.speed as u64) << 32 | monitor.points_per_revolution as u64,
+            // 0 when there hasn't been a frame
+            monitor.last_frame.map_or(0, |t| self.micros(t) + 1),
+        ];
+        for (word, value) in buffer.words.iter().zip(words) {
+            word.store(value, Ordering::Relaxed);
+        }
+        let bins = buffer.words[MONITOR_WORDS..].chunks(2);
+        for (words, bin) in bins.zip(&snapshot.bins) {
+            let (point, time) = match bin {
+                // bit 56 marks a point
+                Some(p) => (
+                    1 << 56
+                        | (p.intensity as u64) << 48
+                        | (p.distance as u64) << 32
+                        | p.angle.to_bits() as u64,
+                    self.micros(p.time),
+                ),
+                None => (0, 0),
+            };
+            words[0].store(point, Ordering::Relaxed);
+            words[1].store(time, Ordering::Relaxed);
+        }
+
+        buffer
+            .sequence
+            .store(sequence.wrapping_add(2), Ordering::Release);
+        self.current.store(next, Ordering::Release);
+    }
+
+    pub fn read(&self) -> Snapshot {
+        let mut values = vec![0u64; self.buffers[0].words.len()];
+        let mut attempt = 0;
+        loop {
+            let buffer = &self.buffers[self.current.load(Ordering::Acquire)];
+            let sequence = buffer.sequence.load(Ordering::Acquire);
+            if sequence & 1 == 0 {
+                for (value, word) in values.iter_mut().zip(&buffer.words) {
+                    *value = word.load(Ordering::Relaxed);
+                }
+                fence(Ordering::Acquire);
+                if buffer.sequence.load(Ordering::Relaxed) == sequence {
+                    break;
+                }
+            }
+            backoff(attempt);
+            attempt += 1;
+        }
+
+        let mut snapshot = Snapshot::new(self.config);
+        snapshot.monitor = Monitor {
+            frames: (values[0] >> 32) as u32,
+            errors: values[0] as u32,
+            speed: (values[1] >> 32) as u16,
+            points_per_revolution: values[1] as u32,
+            last_frame: (values[2] > 0).then(|| self.time(values[2] - 1)),
+            ..Monitor::default()
+        };
+        let bins = values[MONITOR_WORDS..].chunks(2);
+        for (bin, words) in snapshot.bins.iter_mut().zip(bins) {
+            *bin = (words[0] >> 56 == 1).then(|| ScanPoint {
+                angle: f32::from_bits(words[0] as u32),
+                distance: (words[0] >> 32) as u16,
+                intensity: (words[0] >> 48) as u8,
+                time: self.time(words[1]),
+            });
+        }
+        snapshot
+    }
+}
+
+fn backoff(attempt: u32) {
+    if attempt < SPINS {
+        std::hint::spin_loop();
+    } else if attempt < SPINS + YIELDS {
+        thread::yield_now();
+    } else {
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    use super::*;
+
+    // every bin and the monitor carry the same number, a torn read mixes two
+    fn snapshot(config: Config, number: u32, time: Instant) -> Snapshot {
+        let mut snapshot = Snapshot::new(config);
+        snapshot.monitor.frames = number;
+        snapshot.monitor.errors = number;
+        snapshot.monitor.last_frame = Some(time);
+        for (i, bin) in snapshot.bins.iter_mut().enumerate() {
+            *bin = Some(ScanPoint {
+                angle: i as f32 * config.resolution,
+                distance: number as u16,
+                intensity: number as u8,
+                time,
+            });
+        }
+        snapshot
+    }
+
+    #[test]
+    fn reads_back_what_was_published() {
+        let config = Config::default();
+        let published = Published::new(config);
+        let empty = published.read();
+        assert!(empty.bins.iter().all(Option::is_none));
+        assert_eq!(empty.monitor.last_frame, None);
+
+        let time = Instant::now();
+        published.publish(&snapshot(config, 7, time));
+        let read = published.read();
+        assert_eq!(read.monitor.frames, 7);
+        // times are kept to the microsecond
+        let last_frame = read.monitor.last_frame.unwrap();
+        assert!(time.duration_since(last_frame) < Duration::from_micros(1));
+        let written = snapshot(config, 7, last_frame);
+        assert_eq!(read.bins, written.bins);
+    }
+
+    #[test]
+    fn never_returns_a_torn_snapshot() {
+        let config = Config::default();
+        let published = Arc::new(Published::new(config));
+        let done = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let published = published.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut number = 0;
+                while !done.load(Ordering::Relaxed) {
+                    number += 1;
+                    published.publish(&snapshot(config, number, Instant::now()));
+                }
+                number
+            })
+        };
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let published = published.clone();
+                let done = done.clone();
+                thread::spawn(move || {
+                    let mut reads = 0;
+                    let mut last = 0;
+                    while !done.load(Ordering::Relaxed) {
+                        let snapshot = published.read();
+                        let number = snapshot.monitor.frames;
+                        assert_eq!(snapshot.monitor.errors, number);
+                        assert!(number >= last, "went back from {last} to {number}");
+                        for bin in &snapshot.bins {
+                            match bin {
+                                Some(point) => {
+                                    assert_eq!(point.distance, number as u16);
+                                    assert_eq!(point.intensity, number as u8);
+                                    assert_eq!(Some(point.time), snapshot.monitor.last_frame);
+                                }
+                                None => assert_eq!(number, 0),
+                            }
+                        }
+                        last = number;
+                        reads += 1;
+                    }
+                    reads
+                })
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(500));
+        done.store(true, Ordering::Relaxed);
+        let published = writer.join().unwrap();
+        assert!(published > 0);
+        for reader in readers {
+            assert!(reader.join().unwrap() > 0);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Packet;
+use crate::Sample;
+
+// Log of raw packets from any of the protocols, little endian:
+//   header: "LIDR" version:u8
+//   record: time:u64 (us since the recording started) speed:u16 count:u16
+//           then count samples of angle:u16 distance:u16 intensity:u8
+const MAGIC: &[u8; 4] = b"LIDR";
+const VERSION: u8 = 1;
+const SAMPLE_SIZE: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub time: Duration, // since the recording started
+    pub packet: Packet,
+}
+
+pub struct Recorder<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, packet: &Packet, received: Instant) -> std::io::Result<()> {
+        let time = received.saturating_duration_since(self.start).as_micros() as u64;
+        let mut buffer = Vec::with_capacity(12 + packet.samples.len() * SAMPLE_SIZE);
+        buffer.extend_from_slice(&time.to_le_bytes());
+        buffer.extend_from_slice(&packet.speed.to_le_bytes());
+        buffer.extend_from_slice(&(packet.samples.len() as u16).to_le_bytes());
+        for sample in &packet.samples {
+            buffer.extend_from_slice(&sample.angle.to_le_bytes());
+            buffer.extend_from_slice(&sample.distance.to_le_bytes());
+            buffer.push(sample.intensity);
+        }
+        self.writer.write_all(&buffer)
+    }
+
+    pub fn into_inner(mut self) -> std::io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Reads the records back, ends at the end of the log.
+pub struct Replay<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> Replay<R> {
+    pub fn new(mut reader: R) -> std::io::Result<Self> {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "not a lidar log",
+            ));
+        }
+        Ok(Self { reader })
+    }
+
+    fn read_record(&mut self) -> std::io::Result<Option<Record>> {
+        let mut time = [0u8; 8];
+        // a clean end of the log is at a record boundary
+        match self.reader.read(&mut time[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut time[1..])?,
+        }
+        let time = Duration::from_micros(u64::from_le_bytes(time));
+        let packet = self.read_packet()?;
+        Ok(Some(Record { time, packet }))
+    }
+
+    fn read_packet(&mut self) -> std::io::Result<Packet> {
+        let mut header = [0u8; 4];
+        self.reader.read_exact(&mut header)?;
+        let count = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let mut buffer = vec![0u8; count * SAMPLE_SIZE];
+        self.reader.read_exact(&mut buffer)?;
+        let samples = buffer
+            .chunks_exact(SAMPLE_SIZE)
+            .map(|bytes| Sample {
+                angle: u16::from_le_bytes([bytes[0], bytes[1]]),
+                distance: u16::from_le_bytes([bytes[2], bytes[3]]),
+                intensity: bytes[4],
+            })
+            .collect();
+        Ok(Packet {
+            speed: u16::from_le_bytes([header[0], header[1]]),
+            samples,
+        })
+    }
+}
+
+impl<R: Read> Iterator for Replay<R> {
+    type Item = std::io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let packets = [
+            Packet {
+                speed: 3600,
+                samples: vec![
+                    Sample {
+                        angle: 100,
+                        distance: 1000,
+                        intensity: 200,
+                    },
+                    Sample {
+                        angle: 180,
+                        distance: 0,
+                        intensity: 0,
+                    },
+                ],
+            },
+            // an RPLidar packet, no speed and fewer samples
+            Packet {
+                speed: 0,
+                samples: vec![Sample {
+                    angle: 35900,
+                    distance: 950,
+                    intensity: 188,
+                }],
+            },
+        ];
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        let start = Instant::now();
+        for (i, packet) in packets.iter().enumerate() {
+            let received = start + Duration::from_millis(i as u64 * 10);
+            recorder.record(packet, received).unwrap();
+        }
+        let log = recorder.into_inner().unwrap();
+        let records: Vec<Record> = Replay::new(log.as_slice())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let replayed: Vec<Packet> = records.iter().map(|r| r.packet.clone()).collect();
+        assert_eq!(replayed, packets);
+        assert!(records[1].time > records[0].time);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Replay::new(&b"LD19\x01"[..]).is_err());
+        assert!(Replay::new(&b"LIDR\x02"[..]).is_err());
+        assert!(Replay::new(&b"LIDR"[..]).is_err());
+        assert!(Replay::new(&b"LIDR\x01"[..]).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn truncated_record_is_an_error() {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        let packet = Packet {
+            speed: 3600,
+            samples: vec![],
+        };
+        recorder.record(&packet, Instant::now()).unwrap();
+        let log = recorder.into_inner().unwrap();
+        let mut replay = Replay::new(&log[..log.len() - 1]).unwrap();
+        assert!(replay.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Instant;
+
+use crate::deskew::deskew_point;
+use crate::Mount;
+use crate::Point;
+use crate::Pose;
+
+// A single return, angle is in degrees clockwise with 0 straight ahead like the LD19 reports it.
+// In a `Scan` straight ahead is the rover's front, the distance is still from the sensor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanPoint {
+    pub angle: f32,    // degrees
+    pub distance: u16, // mm
+    pub intensity: u8, // LD19 confidence, 0..=255
+    pub time: Instant, // when the point was measured
+}
+
+// Snapshot of the latest point in each angular bin, ordered by angle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scan {
+    resolution: f32,
+    mount: Mount,
+    points: Vec<ScanPoint>,
+}
+
+impl Scan {
+    pub fn new(resolution: f32, mount: Mount, points: Vec<ScanPoint>) -> Self {
+        Self {
+            resolution,
+            mount,
+            points,
+        }
+    }
+
+    // degrees per bin
+    pub fn resolution(&self) -> f32 {
+        self.resolution
+    }
+
+    pub fn points(&self) -> &[ScanPoint] {
+        &self.points
+    }
+
+    // the points in the base frame, x forward and y left of the rover's center
+    pub fn base_points(&self) -> Vec<Point> {
+        self.points
+            .iter()
+            .map(|p| self.mount.point(p.angle, p.distance))
+            .collect()
+    }
+
+    // The points as seen from where the rover was at `reference`, undoing how it moved while they
+    // were measured. `pose_at` looks up the odometry, points it has no pose for are dropped and
+    // it's None when there is no pose for `reference`.
+    pub fn deskew(
+        &self,
+        reference: Instant,
+        pose_at: impl Fn(Instant) -> Option<Pose>,
+    ) -> Option<Scan> {
+        let reference = pose_at(reference)?;
+        let mut points: Vec<ScanPoint> = self
+            .points
+            .iter()
+            .filter_map(|p| {
+                pose_at(p.time).map(|pose| deskew_point(&self.mount, p, &pose, &reference))
+            })
+            .collect();
+        points.sort_by(|a, b| a.angle.total_cmp(&b.angle));
+        Some(Scan::new(self.resolution, self.mount, points))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+}
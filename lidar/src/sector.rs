@@ -0,0 +1,69 @@
+// How the distances in a sector are reduced to a single range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Statistic {
+    Min,
+    Percentile(u8), // 0..=100, a low percentile ignores the odd stray point that Min would catch
+    Mean,
+}
+
+impl Statistic {
+    // `distances` is reordered
+    pub fn apply(&self, distances: &mut [u16]) -> Option<u16> {
+        if distances.is_empty() {
+            return None;
+        }
+        match *self {
+            Statistic::Min => distances.iter().copied().min(),
+            Statistic::Percentile(percentile) => {
+                distances.sort_unstable();
+                let last = distances.len() - 1;
+                let index = (percentile.min(100) as f32 / 100.0 * last as f32).round() as usize;
+                Some(distances[index])
+            }
+            Statistic::Mean => {
+                let sum: u32 = distances.iter().map(|d| *d as u32).sum();
+                Some((sum / distances.len() as u32) as u16)
+            }
+        }
+    }
+}
+
+// Named angular window, degrees clockwise with 0 straight ahead. A window with `start` > `end`
+// wraps through 0, `start` is inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sector {
+    pub name: &'static str,
+    pub start: f32,
+    pub end: f32,
+    pub statistic: Statistic,
+}
+
+impl Sector {
+    pub const fn new(name: &'static str, start: f32, end: f32, statistic: Statistic) -> Self {
+        Self {
+            name,
+            start,
+            end,
+            statistic,
+        }
+    }
+
+    pub fn contains(&self, angle: f32) -> bool {
+        contains(self.start, self.end, angle)
+    }
+}
+
+pub(crate) fn contains(start: f32, end: f32, angle: f32) -> bool {
+    if start <= end {
+        angle >= start && angle < end
+    } else {
+        angle >= start || angle < end
+    }
+}
+
+// 90 degree windows to the left, front and right
+pub const DEFAULT_SECTORS: &[Sector] = &[
+    Sector::new("left", 225.0, 315.0, Statistic::Min),
+    Sector::new("front", 315.0, 45.0, Statistic::Min),
+    Sector::new("right", 45.0, 135.0, Statistic::Min),
+];
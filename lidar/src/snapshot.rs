@@ -0,0 +1,147 @@
+use std::time::Instant;
+
+use crate::deskew::deskew_point;
+use crate::sector;
+use crate::stats::Monitor;
+use crate::Config;
+use crate::Frame;
+use crate::Pose;
+use crate::Scan;
+use crate::ScanPoint;
+use crate::Sector;
+use crate::Statistic;
+use crate::Stats;
+
+// The bins and counters as of one moment, what the range queries run on.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub(crate) config: Config,
+    pub(crate) bins: Vec<Option<ScanPoint>>,
+    pub(crate) monitor: Monitor,
+}
+
+impl Snapshot {
+    pub fn new(config: Config) -> Self {
+        let count = (360.0 / config.resolution).ceil() as usize;
+        Self {
+            config,
+            bins: vec![None; count],
+            monitor: Monitor::default(),
+        }
+    }
+
+    // the bin a point at `angle` degrees from the rover's front goes in
+    pub(crate) fn bin(&self, angle: f32) -> usize {
+        match (angle / self.config.resolution) as usize {
+            x if x >= self.bins.len() => self.bins.len() - 1,
+            x => x,
+        }
+    }
+
+    fn is_fresh(&self, point: &ScanPoint, now: Instant) -> bool {
+        now.saturating_duration_since(point.time) <= self.config.max_age
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.monitor.stats(Instant::now())
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.stats().is_healthy(&self.config.health)
+    }
+
+    // the valid points that aren't stale, only smoothed, the other filters are for the ranges
+    pub fn scan(&self) -> Scan {
+        let now = Instant::now();
+        let points = self
+            .bins
+            .iter()
+            .flatten()
+            .filter(|p| self.is_fresh(p, now))
+            .copied()
+            .collect();
+        Scan::new(self.config.resolution, self.config.mount, points)
+    }
+
+    // The bins as seen from where the rover was at `reference`, see `Scan::deskew`. When points
+    // land in the same bin the newer one is kept.
+    pub fn deskew(
+        &self,
+        reference: Instant,
+        pose_at: impl Fn(Instant) -> Option<Pose>,
+    ) -> Option<Snapshot> {
+        let reference = pose_at(reference)?;
+        let mut snapshot = Snapshot {
+            config: self.config,
+            bins: vec![None; self.bins.len()],
+            monitor: self.monitor,
+        };
+        for point in self.bins.iter().flatten() {
+            let Some(pose) = pose_at(point.time) else {
+                continue;
+            };
+            let point = deskew_point(&self.config.mount, point, &pose, &reference);
+            let index = snapshot.bin(point.angle);
+            let bin = &mut snapshot.bins[index];
+            if !bin.is_some_and(|p| p.time > point.time) {
+                *bin = Some(point);
+            }
+        }
+        Some(snapshot)
+    }
+
+    // ranges of the valid, fresh points by bin, filtered
+    fn ranges(&self) -> Vec<Option<u16>> {
+        let now = Instant::now();
+        let mut ranges: Vec<Option<u16>> = self
+            .bins
+            .iter()
+            .map(|bin| bin.filter(|p| self.is_fresh(p, now)).map(|p| p.distance))
+            .collect();
+        for filter in self.config.filters {
+            ranges = filter.apply(&ranges);
+        }
+        ranges
+    }
+
+    // the ranges in the bins that start in start..end degrees
+    fn distances(&self, ranges: &[Option<u16>], start: f32, end: f32) -> Vec<u16> {
+        ranges
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| sector::contains(start, end, *i as f32 * self.config.resolution))
+            .filter_map(|(_, range)| *range)
+            .collect()
+    }
+
+    // closest range in start..end degrees, wrapping through 0 when start > end, None if there
+    // is no data
+    pub fn range_in(&self, start: f32, end: f32) -> Option<u16> {
+        Statistic::Min.apply(&mut self.distances(&self.ranges(), start, end))
+    }
+
+    pub fn sector_range(&self, sector: &Sector) -> Option<u16> {
+        self.sector_range_in(&self.ranges(), sector)
+    }
+
+    fn sector_range_in(&self, ranges: &[Option<u16>], sector: &Sector) -> Option<u16> {
+        sector
+            .statistic
+            .apply(&mut self.distances(ranges, sector.start, sector.end))
+    }
+
+    pub fn frame(&self, sectors: &[Sector]) -> Frame {
+        let ranges = self.ranges();
+        Frame::new(
+            sectors
+                .iter()
+                .map(|sector| (sector.name, self.sector_range_in(&ranges, sector)))
+                .collect(),
+        )
+    }
+
+    // the sectors from the config
+    pub fn get_frame(&self) -> Frame {
+        self.frame(self.config.sectors)
+    }
+}
@@ -0,0 +1,111 @@
+use hal::PwmOutput;
+use log::*;
+use pid::Pid;
+
+// Closed loop on the speed the LD19 reports, driving its PWM input. Slower means more points per
+// revolution, faster means fresher revolutions.
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedConfig {
+    pub speed: u16,     // degrees per second to start at, 3600 is 10Hz
+    pub min_speed: u16, // `set_speed` is clamped to min_speed..=max_speed
+    pub max_speed: u16,
+    pub duty: f32, // percent, roughly where the LD19 runs at `speed`
+    pub p: f32,    // percent per degree per second
+    pub i: f32,
+}
+
+impl Default for SpeedConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl SpeedConfig {
+    pub const DEFAULT: Self = Self {
+        speed: 3600,
+        min_speed: 3000,
+        max_speed: 4320,
+        duty: 40.0,
+        p: 0.002,
+        i: 0.001,
+    };
+
+    pub fn clamp(&self, speed: u16) -> u16 {
+        speed.clamp(self.min_speed, self.max_speed)
+    }
+
+    // `motion` is how fast the rover moves, 0 standing still to 1 flat out. Standing still the
+    // lidar runs at min_speed for the most points, flat out at max_speed for the freshest ranges.
+    pub fn for_motion(&self, motion: f32) -> u16 {
+        let span = self.max_speed.saturating_sub(self.min_speed) as f32;
+        self.min_speed + (motion.clamp(0.0, 1.0) * span).round() as u16
+    }
+}
+
+pub(crate) struct SpeedControl {
+    pwm: Box<dyn PwmOutput>,
+    pid: Pid<f32>,
+    duty: f32,
+}
+
+impl SpeedControl {
+    pub fn new(pwm: Box<dyn PwmOutput>, config: SpeedConfig) -> Self {
+        // the output is a correction on top of the feed forward duty
+        let pid = Pid::new(
+            config.p,
+            config.i,
+            0.0,
+            100.0,
+            100.0,
+            100.0,
+            100.0,
+            config.speed as f32,
+        );
+        Self {
+            pwm,
+            pid,
+            duty: config.duty,
+        }
+    }
+
+    // once a revolution, with the speed from the last frame
+    pub fn update(&mut self, target: u16, speed: u16) {
+        self.pid.setpoint = target as f32;
+        let output = self.pid.next_control_output(speed as f32);
+        trace!("lidar speed: target={target} speed={speed} output={output:?}");
+        self.set_duty(self.duty + output.output);
+    }
+
+    // back to the feed forward duty, while the lidar is off or spinning up
+    pub fn reset(&mut self) {
+        self.pid.reset_integral_term();
+        self.set_duty(self.duty);
+    }
+
+    fn set_duty(&mut self, percent: f32) {
+        let max = self.pwm.get_max_duty();
+        let duty = (percent.clamp(0.0, 100.0) / 100.0 * max as f32) as u32;
+        if let Err(err) = self.pwm.set_duty(duty) {
+            warn!("lidar speed: {err:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faster_on_the_move() {
+        let config = SpeedConfig {
+            min_speed: 3240,
+            max_speed: 4320,
+            ..SpeedConfig::DEFAULT
+        };
+        assert_eq!(config.for_motion(0.0), 3240);
+        assert_eq!(config.for_motion(0.5), 3780);
+        assert_eq!(config.for_motion(1.0), 4320);
+        assert_eq!(config.for_motion(2.0), 4320);
+        assert_eq!(config.for_motion(-1.0), 3240);
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+use std::time::Instant;
+
+// Limits past which the sensor is unhealthy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub frame_timeout: Duration, // longest gap between frames
+    pub min_speed: u16,          // degrees per second
+    pub min_points: u32,         // per revolution
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::LD19
+    }
+}
+
+impl Health {
+    // runs at 3600 with about 450 points a revolution, the LD06 too
+    pub const LD19: Self = Self {
+        frame_timeout: Duration::from_millis(200),
+        min_speed: 3000,
+        min_points: 350,
+    };
+
+    // runs at about 2000 (5.5Hz) with about 360 points a revolution from a standard SCAN
+    pub const RPLIDAR_A1: Self = Self {
+        frame_timeout: Duration::from_millis(200),
+        min_speed: 1600,
+        min_points: 250,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub frames: u32,
+    pub errors: u32,                // CRC and parse errors
+    pub speed: u16,                 // degrees per second, from the last frame or revolution
+    pub points_per_revolution: u32, // in the last full revolution
+    pub since_last_frame: Option<Duration>,
+}
+
+impl Stats {
+    pub fn is_healthy(&self, health: &Health) -> bool {
+        self.since_last_frame
+            .is_some_and(|since| since <= health.frame_timeout)
+            && self.speed >= health.min_speed
+            && self.points_per_revolution >= health.min_points
+    }
+}
+
+// Counts frames as they are added to `Data`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Monitor {
+    pub frames: u32,
+    pub errors: u32,
+    pub speed: u16,
+    pub last_frame: Option<Instant>,
+    pub last_angle: u16,
+    pub last_revolution: Option<Instant>,
+    pub points: u32, // in the revolution so far
+    pub points_per_revolution: u32,
+}
+
+impl Monitor {
+    // True when the frame starts a new revolution. Sensors that don't report their speed pass 0,
+    // it's then measured from the revolutions.
+    pub fn frame(&mut self, speed: u16, start_angle: u16, points: u32, received: Instant) -> bool {
+        self.frames += 1;
+        if speed > 0 {
+            self.speed = speed;
+        }
+        self.last_frame = Some(received);
+        // a new revolution starts when the angle wraps, not when it jitters back a little
+        let revolution = (start_angle as u32) + 18000 < self.last_angle as u32;
+        if revolution {
+            self.points_per_revolution = self.points;
+            self.points = 0;
+            if let (0, Some(last)) = (speed, self.last_revolution) {
+                let period = received.saturating_duration_since(last).as_secs_f32();
+                self.speed = (360.0 / period).min(u16::MAX as f32) as u16;
+            }
+            self.last_revolution = Some(received);
+        }
+        self.last_angle = start_angle;
+        self.points += points;
+        revolution
+    }
+
+    pub fn error(&mut self) {
+        self.errors += 1;
+    }
+
+    pub fn stats(&self, now: Instant) -> Stats {
+        Stats {
+            frames: self.frames,
+            errors: self.errors,
+            speed: self.speed,
+            points_per_revolution: self.points_per_revolution,
+            since_last_frame: self
+                .last_frame
+                .map(|last| now.saturating_duration_since(last)),
+        }
+    }
+}
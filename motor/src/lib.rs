@@ -1,21 +1,29 @@
-use esp_idf_hal::gpio::Output;
+#[cfg(feature = "esp")]
 use esp_idf_hal::gpio::OutputPin;
+#[cfg(feature = "esp")]
 use esp_idf_hal::gpio::PinDriver;
+#[cfg(feature = "esp")]
 use esp_idf_hal::ledc::*;
+#[cfg(feature = "esp")]
 use esp_idf_hal::peripheral::Peripheral;
+#[cfg(feature = "esp")]
 use esp_idf_sys::EspError;
 
+use hal::DigitalOutput;
+use hal::HalError;
+use hal::PwmOutput;
 use log::*;
 
-pub struct Motor<'d, PH: OutputPin> {
+pub struct Motor<'d> {
     name: String,
-    pin_ph: PinDriver<'d, PH, Output>,
-    pwm: LedcDriver<'d>,
+    pin_ph: Box<dyn DigitalOutput + 'd>,
+    pwm: Box<dyn PwmOutput + 'd>,
     invert: bool,
 }
 
-impl<'d, PH: OutputPin> Motor<'d, PH> {
-    pub fn new<EN, C>(
+impl<'d> Motor<'d> {
+    #[cfg(feature = "esp")]
+    pub fn new<PH, EN, C>(
         name: &str,
         channel: impl Peripheral<P = C> + 'd,
         timer_driver: &LedcTimerDriver<'d>,
@@ -24,20 +32,36 @@ impl<'d, PH: OutputPin> Motor<'d, PH> {
         invert: bool,
     ) -> Result<Self, EspError>
     where
+        PH: OutputPin + 'd,
         EN: OutputPin + 'd,
         C: LedcChannel,
     {
         let pwm = LedcDriver::new(channel, timer_driver, enable)?;
-        Ok(Motor {
-            name: name.to_string(),
-            pin_ph: PinDriver::output(phase)?,
+        Ok(Self::with_driver(
+            name,
+            PinDriver::output(phase)?,
             pwm,
             invert,
-        })
+        ))
     }
 
-    pub fn set_percent(&mut self, percent: f32) -> Result<(), EspError> {
-        let duty = (self.pwm.get_max_duty() as f32 * percent.abs() / (100f32)) as u32;
+    pub fn with_driver(
+        name: &str,
+        phase: impl DigitalOutput + 'd,
+        pwm: impl PwmOutput + 'd,
+        invert: bool,
+    ) -> Self {
+        Motor {
+            name: name.to_string(),
+            pin_ph: Box::new(phase),
+            pwm: Box::new(pwm),
+            invert,
+        }
+    }
+
+    pub fn set_percent(&mut self, percent: f32) -> Result<(), HalError> {
+        // the LEDC driver rejects a duty above its maximum
+        let duty = (self.pwm.get_max_duty() as f32 * percent.abs().min(100.0) / (100f32)) as u32;
         let direction = percent.signum() as i8 * if self.invert { -1 } else { 1 };
         trace!("set_percent: percent={percent} duty={duty} direction={direction}");
         match direction {
@@ -52,3 +76,58 @@ impl<'d, PH: OutputPin> Motor<'d, PH> {
         &self.name
     }
 }
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use hal::host::SimPin;
+    use hal::host::SimPwm;
+
+    use super::*;
+
+    fn motor(invert: bool) -> (Motor<'static>, SimPin, SimPwm) {
+        let phase = SimPin::new();
+        let pwm = SimPwm::new(1000);
+        let motor = Motor::with_driver("test", phase.clone(), pwm.clone(), invert);
+        (motor, phase, pwm)
+    }
+
+    #[test]
+    fn forward_is_phase_low() {
+        let (mut motor, phase, pwm) = motor(false);
+        motor.set_percent(50.0).unwrap();
+        assert_eq!(pwm.get_duty(), 500);
+        assert!(!phase.is_high());
+    }
+
+    #[test]
+    fn reverse_is_phase_high() {
+        let (mut motor, phase, pwm) = motor(false);
+        motor.set_percent(-25.0).unwrap();
+        assert_eq!(pwm.get_duty(), 250);
+        assert!(phase.is_high());
+    }
+
+    #[test]
+    fn invert_swaps_the_phase() {
+        let (mut motor, phase, _) = motor(true);
+        motor.set_percent(50.0).unwrap();
+        assert!(phase.is_high());
+        motor.set_percent(-50.0).unwrap();
+        assert!(!phase.is_high());
+    }
+
+    #[test]
+    fn zero_is_no_duty() {
+        let (mut motor, _, pwm) = motor(false);
+        motor.set_percent(-50.0).unwrap();
+        motor.set_percent(0.0).unwrap();
+        assert_eq!(pwm.get_duty(), 0);
+    }
+
+    #[test]
+    fn duty_is_limited_to_full() {
+        let (mut motor, _, pwm) = motor(false);
+        motor.set_percent(150.0).unwrap();
+        assert_eq!(pwm.get_duty(), 1000);
+    }
+}
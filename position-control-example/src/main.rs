@@ -11,6 +11,7 @@ use log::*;
 
 use encoder::Encoder;
 use motor::Motor;
+use position_control::CommandId;
 use position_control::PositionControl;
 use position_control::PositionControlCmd;
 use speed_control::SpeedControl;
@@ -29,6 +30,8 @@ const POSITION_CONFIG: position_control::Config = position_control::Config {
     p: 0.5,
     i: 0.01,
     d: 0.2,
+    acceleration: 1440.0,
+    jerk: Some(7200.0),
 };
 
 fn main() -> anyhow::Result<()> {
@@ -101,10 +104,16 @@ fn main() -> anyhow::Result<()> {
     let position_right = PositionControl::new(speed_right, POSITION_CONFIG, 720.0)?;
 
     let mut reporter = PositinReporter::new();
+    position_left
+        .notifier()
+        .on_complete(|c| info!("left: {} {:?}", c.id, c.status));
+    position_right
+        .notifier()
+        .on_complete(|c| info!("right: {} {:?}", c.id, c.status));
 
     info!("forward");
-    position_left.send(PositionControlCmd::SetPosition(10000))?;
-    position_right.send(PositionControlCmd::SetPosition(10000))?;
+    position_left.send(PositionControlCmd::SetPosition((CommandId::next(), 10000)))?;
+    position_right.send(PositionControlCmd::SetPosition((CommandId::next(), 10000)))?;
     while position_left.is_active() && position_right.is_active() {
         reporter.update(position_left.get_position(), position_right.get_position());
         delay.delay_ms(1000);
@@ -112,8 +121,8 @@ fn main() -> anyhow::Result<()> {
     info!("done");
     delay.delay_ms(1000);
     info!("reverse");
-    position_left.send(PositionControlCmd::SetPosition(0))?;
-    position_right.send(PositionControlCmd::SetPosition(0))?;
+    position_left.send(PositionControlCmd::SetPosition((CommandId::next(), 0)))?;
+    position_right.send(PositionControlCmd::SetPosition((CommandId::next(), 0)))?;
     while position_left.is_active() && position_right.is_active() {
         reporter.update(position_left.get_position(), position_right.get_position());
         delay.delay_ms(1000);
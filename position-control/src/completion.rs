@@ -0,0 +1,72 @@
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+
+use hal::Callbacks;
+use log::*;
+
+// Identifies a move, unique for the life of the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommandId(u32);
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+impl CommandId {
+    pub fn next() -> Self {
+        CommandId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl std::fmt::Display for CommandId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Completed,
+    Cancelled, // stopped or replaced by another command
+    Failed,    // didn't reach the target
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completion {
+    pub id: CommandId,
+    pub status: Status,
+}
+
+// Completion callbacks, clones share the callbacks.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    callbacks: Callbacks<Completion>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the callback runs on the thread that finishes the command, keep it short
+    pub fn on_complete(&self, callback: impl Fn(Completion) + Send + Sync + 'static) {
+        self.callbacks.add(callback);
+    }
+
+    // completions are sent for as long as the receiver is kept
+    pub fn subscribe(&self) -> Receiver<Completion> {
+        self.callbacks.subscribe()
+    }
+
+    pub fn notify(&self, id: CommandId, status: Status) {
+        debug!("{id} {status:?}");
+        self.callbacks.call(Completion { id, status });
+    }
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier")
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
@@ -1,4 +1,3 @@
-use std::ptr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
@@ -6,33 +5,42 @@ use std::sync::mpsc::Receiver;
 use std::sync::mpsc::SendError;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
-use esp_idf_svc::timer::EspTaskTimerService;
-use esp_idf_sys::vTaskPrioritySet;
-use esp_idf_sys::EspError;
-use esp_idf_sys::TaskHandle_t;
-use esp_idf_sys::ESP_TASK_PRIO_MAX;
+use hal::HalError;
+use hal::Priority;
 use log::*;
 use pid::Pid;
 
 use speed_control::SpeedControl;
 use speed_control::SpeedControlCmd;
 
+mod completion;
+mod profile;
+
+pub use completion::CommandId;
+pub use completion::Completion;
+pub use completion::Notifier;
+pub use completion::Status;
+pub use profile::Profile;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
     pub interval: Duration,
     pub p: f64,
     pub i: f64,
     pub d: f64,
+    pub acceleration: f64, // degrees per second per second
+    pub jerk: Option<f64>, // degrees per second^3, None for a trapezoidal profile
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum PositionControlCmd {
     Tick(u64), // tick from timer.
-    SetPosition(i64),
+    SetPosition((CommandId, i64)),
     SetPid((f64, f64, f64)),
+    SetMaxSpeed(Option<f32>), // degrees per second, None restores the configured max speed
     Stop,
     SpeedCmd(SpeedControlCmd),
 }
@@ -42,8 +50,13 @@ pub struct PositionControl<'d> {
     tx: Sender<PositionControlCmd>,
     speed: SpeedControl<'d>,
     active: Arc<AtomicBool>,
+    notifier: Notifier,
+    max_speed: f32,
 }
 
+// seconds after the end of the profile to reach the target before the move fails
+const SETTLE_TIME: f64 = 1.0;
+
 impl<'d> PositionControl<'d> {
     pub fn new(
         speed: SpeedControl<'static>,
@@ -64,17 +77,21 @@ impl<'d> PositionControl<'d> {
         );
         let interval = config.interval;
         let active = Arc::new(AtomicBool::new(false));
+        let notifier = Notifier::new();
         {
             let mut stop = false;
+            let mut current: Option<CommandId> = None;
+            let notifier = notifier.clone();
+            let mut scale = 1.0;
+            let mut profile = Profile::new(0.0, 0.0, 1.0, 1.0, None);
+            let mut start = Instant::now();
             let speed = speed.clone();
             let active = active.clone();
             let tx = tx.clone();
-            let timer = EspTaskTimerService::new()?
-                .timer(move || tx.send(PositionControlCmd::Tick(0)).unwrap())?;
+            let timer = hal::timer(move || tx.send(PositionControlCmd::Tick(0)).unwrap())?;
 
-            thread::Builder::new().stack_size(4096).spawn(move || {
+            hal::spawn("position", 4096, Priority::High, move || {
                 let name = speed.get_name();
-                unsafe { vTaskPrioritySet(ptr::null_mut() as TaskHandle_t, ESP_TASK_PRIO_MAX - 2) }
 
                 loop {
                     match rx.recv().unwrap() {
@@ -85,34 +102,67 @@ impl<'d> PositionControl<'d> {
                             let position: f64 = speed.get_position() as f64;
                             trace!("{name}: position:{position}");
 
-                            if stop || (position - pid.setpoint).abs() <= 2.0 {
-                                info!(
-                                    "{name}: stopping at {position} setpoint={} stop={stop}",
-                                    pid.setpoint
-                                );
+                            let t = start.elapsed().as_secs_f64();
+                            let target = profile.target();
+                            let status = if stop {
+                                Some(Status::Cancelled)
+                            } else if t >= profile.duration() && (position - target).abs() <= 2.0 {
+                                Some(Status::Completed)
+                            } else if t >= profile.duration() + SETTLE_TIME {
+                                Some(Status::Failed)
+                            } else {
+                                None
+                            };
+                            if let Some(status) = status {
+                                info!("{name}: stopping at {position} target={target} {status:?}");
                                 pid.reset_integral_term();
                                 active.store(false, Ordering::Relaxed);
                                 stop = false;
                                 speed.send(SpeedControlCmd::SetSpeed(0.0)).unwrap();
+                                if let Some(id) = current.take() {
+                                    notifier.notify(id, status);
+                                }
                                 continue;
                             }
+                            // the PID corrects the error from the profile, the profile velocity is
+                            // fed forward
+                            pid.setpoint = profile.position(t);
                             let output = pid.next_control_output(position);
+                            let limit = pid.output_limit;
+                            let output = (profile.velocity(t) + output.output).clamp(-limit, limit);
 
-                            trace!("{name}: PID update {:?}", output);
+                            trace!("{name}: setpoint={} output={output}", pid.setpoint);
                             speed
-                                .send(SpeedControlCmd::SetSpeed(output.output as f32))
+                                .send(SpeedControlCmd::SetSpeed(output as f32))
                                 .unwrap();
                         }
                         PositionControlCmd::Stop => {
                             info!("{name}: setting stop=true");
                             stop = true
                         }
-                        PositionControlCmd::SetPosition(position) => {
+                        PositionControlCmd::SetPosition((id, position)) => {
+                            if let Some(previous) = current.replace(id) {
+                                notifier.notify(previous, Status::Cancelled);
+                            }
                             if !timer.is_scheduled().unwrap() {
                                 timer.every(interval).unwrap();
                             }
-                            pid.setpoint = position as f64;
-                            info!("{name}: setpoint={}", &pid.setpoint);
+                            // always starts from rest at the current position
+                            let current = speed.get_position() as f64;
+                            profile = Profile::new(
+                                current,
+                                position as f64,
+                                pid.output_limit,
+                                config.acceleration * scale,
+                                config.jerk.map(|j| j * scale),
+                            );
+                            start = Instant::now();
+                            pid.setpoint = current;
+                            pid.reset_integral_term();
+                            info!(
+                                "{name}: {id} target={position} duration={:.2}s",
+                                profile.duration()
+                            );
                             stop = false;
                             active.store(true, Ordering::Relaxed);
                         }
@@ -121,14 +171,37 @@ impl<'d> PositionControl<'d> {
                             pid.ki = i;
                             pid.kd = d;
                         }
+                        PositionControlCmd::SetMaxSpeed(limit) => {
+                            // acceleration and jerk scale with the speed so a slower wheel
+                            // finishes at the same time as a faster one with a longer move, never
+                            // past the configured limits
+                            scale = (limit.unwrap_or(max_speed) / max_speed).abs().min(1.0) as f64;
+                            pid.output_limit = max_speed.abs() as f64 * scale;
+                            debug!("{name}: max speed={}", pid.output_limit);
+                        }
                         PositionControlCmd::SpeedCmd(cmd) => {
+                            // driving the speed directly takes over from the position loop
+                            if active.swap(false, Ordering::Relaxed) {
+                                info!("{name}: speed command, position control stopped");
+                                pid.reset_integral_term();
+                                stop = false;
+                            }
+                            if let Some(id) = current.take() {
+                                notifier.notify(id, Status::Cancelled);
+                            }
                             speed.send(cmd).unwrap();
                         }
                     }
                 }
             })?;
         }
-        Ok(Self { tx, speed, active })
+        Ok(Self {
+            tx,
+            speed,
+            active,
+            notifier,
+            max_speed,
+        })
     }
 
     pub fn get_position(&self) -> i64 {
@@ -139,6 +212,15 @@ impl<'d> PositionControl<'d> {
         self.active.load(Ordering::Relaxed)
     }
 
+    // degrees per second, as configured
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    pub fn notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
     pub fn send(&self, cmd: PositionControlCmd) -> Result<(), SendError<PositionControlCmd>> {
         self.tx.send(cmd)
     }
@@ -146,7 +228,7 @@ impl<'d> PositionControl<'d> {
 
 #[derive(Debug)]
 pub enum PositionControlError {
-    EspError(EspError),
+    HalError(HalError),
     IOError(std::io::Error),
     SendError(SendError<PositionControlCmd>),
 }
@@ -159,9 +241,9 @@ impl std::fmt::Display for PositionControlError {
 
 impl std::error::Error for PositionControlError {}
 
-impl From<EspError> for PositionControlError {
-    fn from(e: EspError) -> Self {
-        PositionControlError::EspError(e)
+impl From<HalError> for PositionControlError {
+    fn from(e: HalError) -> Self {
+        PositionControlError::HalError(e)
     }
 }
 
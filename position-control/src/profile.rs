@@ -0,0 +1,275 @@
+// Motion profile from rest to rest over `distance`, trapezoidal when there is no jerk limit and
+// an S-curve (jerk limited acceleration) otherwise. Scaling the velocity, acceleration and jerk by
+// the same factor as the distance gives the same timing, that keeps wheels with different
+// distances in step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Profile {
+    start: f64,
+    distance: f64, // signed
+    jerk: f64,
+    acceleration: f64,
+    velocity: f64,       // peak velocity, may be less than the limit for short moves
+    t_jerk: f64,         // duration of each jerk segment
+    t_accel: f64,        // duration of the constant acceleration segment
+    t_cruise: f64,       // duration at peak velocity
+    accel_distance: f64, // distance covered while accelerating (and decelerating)
+}
+
+impl Profile {
+    // `max_velocity` and `max_acceleration` must be > 0, `max_jerk` None for trapezoidal
+    pub fn new(
+        start: f64,
+        target: f64,
+        max_velocity: f64,
+        max_acceleration: f64,
+        max_jerk: Option<f64>,
+    ) -> Self {
+        let distance = target - start;
+        let length = distance.abs();
+        let mut profile = Self {
+            start,
+            distance,
+            jerk: max_jerk.unwrap_or(f64::INFINITY),
+            acceleration: max_acceleration,
+            velocity: max_velocity,
+            t_jerk: 0.0,
+            t_accel: 0.0,
+            t_cruise: 0.0,
+            accel_distance: 0.0,
+        };
+        if length == 0.0 {
+            profile.velocity = 0.0;
+            return profile;
+        }
+        if 2.0 * profile.ramp(max_velocity).2 > length {
+            // can't reach the max velocity, find the peak velocity that covers the distance
+            let (mut low, mut high) = (0.0, max_velocity);
+            for _ in 0..60 {
+                let mid = (low + high) / 2.0;
+                if 2.0 * profile.ramp(mid).2 > length {
+                    high = mid;
+                } else {
+                    low = mid;
+                }
+            }
+            profile.velocity = low;
+        }
+        let (t_jerk, t_accel, accel_distance) = profile.ramp(profile.velocity);
+        profile.t_jerk = t_jerk;
+        profile.t_accel = t_accel;
+        profile.accel_distance = accel_distance;
+        if profile.velocity > 0.0 {
+            profile.t_cruise = (length - 2.0 * accel_distance).max(0.0) / profile.velocity;
+        }
+        profile
+    }
+
+    // (jerk time, constant acceleration time, distance) to accelerate from rest to `velocity`
+    fn ramp(&self, velocity: f64) -> (f64, f64, f64) {
+        let a = self.acceleration;
+        let j = self.jerk;
+        if velocity >= a * a / j {
+            let t_jerk = a / j;
+            let t_accel = velocity / a - t_jerk;
+            (t_jerk, t_accel, velocity * (velocity / a + t_jerk) / 2.0)
+        } else {
+            // the acceleration never reaches its limit
+            let t_jerk = (velocity / j).sqrt();
+            (t_jerk, 0.0, velocity * t_jerk)
+        }
+    }
+
+    fn ramp_duration(&self) -> f64 {
+        2.0 * self.t_jerk + self.t_accel
+    }
+
+    pub fn duration(&self) -> f64 {
+        2.0 * self.ramp_duration() + self.t_cruise
+    }
+
+    pub fn target(&self) -> f64 {
+        self.start + self.distance
+    }
+
+    // position at `t` seconds from the start
+    pub fn position(&self, t: f64) -> f64 {
+        let ramp = self.ramp_duration();
+        let travelled = if t <= 0.0 {
+            0.0
+        } else if t <= ramp {
+            self.ramp_state(t).0
+        } else if t <= ramp + self.t_cruise {
+            self.accel_distance + self.velocity * (t - ramp)
+        } else if t < self.duration() {
+            self.distance.abs() - self.ramp_state(self.duration() - t).0
+        } else {
+            self.distance.abs()
+        };
+        self.start + self.distance.signum() * travelled
+    }
+
+    // velocity at `t` seconds from the start
+    pub fn velocity(&self, t: f64) -> f64 {
+        let ramp = self.ramp_duration();
+        let speed = if t <= 0.0 || t >= self.duration() {
+            0.0
+        } else if t <= ramp {
+            self.ramp_state(t).1
+        } else if t <= ramp + self.t_cruise {
+            self.velocity
+        } else {
+            self.ramp_state(self.duration() - t).1
+        };
+        self.distance.signum() * speed
+    }
+
+    // (distance, velocity) at `t` seconds into the ramp from rest to the peak velocity
+    fn ramp_state(&self, t: f64) -> (f64, f64) {
+        let tj = self.t_jerk;
+        let ta = self.t_accel;
+        let a = self.acceleration;
+        if tj == 0.0 {
+            // trapezoidal
+            return (a * t * t / 2.0, a * t);
+        }
+        let j = self.jerk;
+        let a_peak = j * tj;
+        if t <= tj {
+            return (j * t.powi(3) / 6.0, j * t * t / 2.0);
+        }
+        let s1 = j * tj.powi(3) / 6.0;
+        let v1 = j * tj * tj / 2.0;
+        if t <= tj + ta {
+            let t = t - tj;
+            return (s1 + v1 * t + a_peak * t * t / 2.0, v1 + a_peak * t);
+        }
+        let s2 = s1 + v1 * ta + a_peak * ta * ta / 2.0;
+        let v2 = v1 + a_peak * ta;
+        let t = t - tj - ta;
+        (
+            s2 + v2 * t + a_peak * t * t / 2.0 - j * t.powi(3) / 6.0,
+            v2 + a_peak * t - j * t * t / 2.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    // sampled over the move the position goes one way and the speed stays within the limit
+    fn assert_smooth(profile: &Profile, max_velocity: f64) {
+        let steps = 1000;
+        let mut previous = profile.position(0.0);
+        for i in 1..=steps {
+            let t = profile.duration() * i as f64 / steps as f64;
+            let position = profile.position(t);
+            assert!((position - previous) * profile.distance.signum() >= -1e-9);
+            assert!(profile.velocity(t).abs() <= max_velocity + 1e-9);
+            previous = position;
+        }
+    }
+
+    #[test]
+    fn trapezoid_duration() {
+        // 2s to reach 100, 100 while accelerating and braking, 800 cruising for 8s
+        let profile = Profile::new(0.0, 1000.0, 100.0, 50.0, None);
+        assert!(close(profile.duration(), 12.0));
+        assert!(close(profile.position(2.0), 100.0));
+        assert!(close(profile.velocity(6.0), 100.0));
+        assert_smooth(&profile, 100.0);
+    }
+
+    #[test]
+    fn s_curve_takes_longer_by_the_jerk_time() {
+        let trapezoid = Profile::new(0.0, 1000.0, 100.0, 50.0, None);
+        let s_curve = Profile::new(0.0, 1000.0, 100.0, 50.0, Some(100.0));
+        // reaching the acceleration takes 50 / 100 = 0.5s
+        assert!(close(s_curve.duration(), trapezoid.duration() + 0.5));
+        assert!(close(s_curve.velocity(s_curve.duration() / 2.0), 100.0));
+        assert_smooth(&s_curve, 100.0);
+    }
+
+    #[test]
+    fn starts_and_ends_at_rest() {
+        for jerk in [None, Some(100.0)] {
+            let profile = Profile::new(10.0, 1000.0, 100.0, 50.0, jerk);
+            assert_eq!(profile.position(0.0), 10.0);
+            assert_eq!(profile.position(profile.duration()), profile.target());
+            assert_eq!(profile.target(), 1000.0);
+            assert_eq!(profile.velocity(0.0), 0.0);
+            assert_eq!(profile.velocity(profile.duration()), 0.0);
+            // just inside the ends the speed is close to 0
+            assert!(profile.velocity(1e-3).abs() < 0.1);
+            assert!(profile.velocity(profile.duration() - 1e-3).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn short_move_peaks_below_the_max_velocity() {
+        // 25 up to 50 and 25 down again, 1s each
+        let profile = Profile::new(0.0, 50.0, 100.0, 50.0, None);
+        assert!(close(profile.duration(), 2.0));
+        assert!(close(profile.velocity(1.0), 50.0));
+        assert!(close(profile.position(1.0), 25.0));
+        assert_eq!(profile.position(profile.duration()), 50.0);
+        assert_smooth(&profile, 50.0 + 1e-6);
+
+        let profile = Profile::new(0.0, 50.0, 100.0, 50.0, Some(100.0));
+        let peak = profile.velocity(profile.duration() / 2.0);
+        assert!(peak > 0.0 && peak < 100.0);
+        assert!(close(profile.position(profile.duration() / 2.0), 25.0));
+        assert_eq!(profile.position(profile.duration()), 50.0);
+        assert_smooth(&profile, peak + 1e-6);
+    }
+
+    #[test]
+    fn negative_move_mirrors_the_positive() {
+        for jerk in [None, Some(100.0)] {
+            let forward = Profile::new(0.0, 1000.0, 100.0, 50.0, jerk);
+            let backward = Profile::new(0.0, -1000.0, 100.0, 50.0, jerk);
+            assert_eq!(forward.duration(), backward.duration());
+            for t in [0.5, 2.0, 6.0, 11.0] {
+                assert!(close(backward.position(t), -forward.position(t)));
+                assert!(close(backward.velocity(t), -forward.velocity(t)));
+            }
+            assert_eq!(backward.position(backward.duration()), -1000.0);
+            assert_smooth(&backward, 100.0);
+        }
+    }
+
+    #[test]
+    fn zero_length_move() {
+        for jerk in [None, Some(100.0)] {
+            let profile = Profile::new(42.0, 42.0, 100.0, 50.0, jerk);
+            assert_eq!(profile.duration(), 0.0);
+            assert_eq!(profile.position(0.0), 42.0);
+            assert_eq!(profile.position(1.0), 42.0);
+            assert_eq!(profile.velocity(0.0), 0.0);
+            assert_eq!(profile.velocity(1.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn scaled_limits_keep_the_timing() {
+        for (distance, jerk) in [(1000.0, None), (1000.0, Some(100.0)), (50.0, Some(100.0))] {
+            let profile = Profile::new(0.0, distance, 100.0, 50.0, jerk);
+            for scale in [0.25, 3.0] {
+                let scaled = Profile::new(
+                    0.0,
+                    distance * scale,
+                    100.0 * scale,
+                    50.0 * scale,
+                    jerk.map(|j| j * scale),
+                );
+                assert!(close(scaled.duration(), profile.duration()));
+                let t = profile.duration() / 3.0;
+                assert!(close(scaled.position(t), profile.position(t) * scale));
+            }
+        }
+    }
+}
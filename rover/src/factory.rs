@@ -1,11 +1,13 @@
 use std::time::Duration;
 
 use differential_drive::Drive;
+use differential_drive::DriveError;
 use encoder::Encoder;
 use esp_idf_hal::gpio::AnyInputPin;
 use esp_idf_hal::gpio::AnyOutputPin;
 use esp_idf_hal::ledc;
 use esp_idf_hal::ledc::LedcChannel;
+use esp_idf_hal::ledc::LedcDriver;
 use esp_idf_hal::ledc::LedcTimer;
 use esp_idf_hal::ledc::LedcTimerDriver;
 use esp_idf_hal::ledc::Resolution;
@@ -16,12 +18,17 @@ use esp_idf_hal::uart::Uart;
 use esp_idf_hal::units::FromValueType;
 use esp_idf_hal::units::Hertz;
 use esp_idf_sys::EspError;
+use hal::SharedBus;
 use lidar::Lidar;
 use motor::Motor;
 use position_control::PositionControl;
 use position_control::PositionControlError;
 use speed_control::SpeedControl;
 use speed_control::SpeedControlError;
+use tof::RangeFinder;
+use tof::Tof;
+use tof::TofError;
+use tof::Vl53l1x;
 use wheel::Wheel;
 
 use crate::peripherals::EncoderPeripherals;
@@ -29,10 +36,11 @@ use crate::peripherals::LidarPeripherals;
 use crate::peripherals::MotorPeripherals;
 
 const WHEEL_DIAMETER: i32 = 60; // 60 mm wheel diameter
-const WHEEL_DISTANCE: i32 = 100; // distance bewtween wheels
 
 const TICKS_PER_REVOLUTION: u32 = 1440;
 
+const MAX_WHEEL_SPEED: f32 = 720.0; // degrees per second
+
 const SPEED_CONFIG: speed_control::Config = speed_control::Config {
     interval: Duration::from_millis(100),
     p: 0.5,
@@ -45,6 +53,62 @@ const POSITION_CONFIG: position_control::Config = position_control::Config {
     p: 0.5,
     i: 0.01,
     d: 0.2,
+    acceleration: 1440.0,
+    jerk: Some(7200.0),
+};
+
+const DRIVE_CONFIG: differential_drive::Config = differential_drive::Config {
+    wheel_distance: 100, // distance bewtween wheels
+    odometry_interval: Duration::from_millis(20),
+    slip: 0.01,
+    velocity_timeout: Duration::from_millis(500),
+};
+
+// flat out in mm/s driving straight and rad/s spinning on the spot
+pub const TOP_SPEED: f64 =
+    MAX_WHEEL_SPEED as f64 / 360.0 * std::f64::consts::PI * WHEEL_DIAMETER as f64;
+pub const TOP_TURN_RATE: f64 = 2.0 * TOP_SPEED / DRIVE_CONFIG.wheel_distance as f64;
+
+const LIDAR_CONFIG: lidar::Config = lidar::Config {
+    resolution: 1.0,
+    min_confidence: 100,
+    max_age: Duration::from_millis(500),
+    sectors: lidar::DEFAULT_SECTORS,
+    mount: lidar::Mount::CENTERED,
+    mask: &[],
+    health: lidar::Health {
+        frame_timeout: Duration::from_millis(200),
+        min_speed: 3000,
+        min_points: 350,
+    },
+    // follows the rover's motion, 9Hz standing still to 12Hz flat out. Both stay clear of the
+    // health limits, 9Hz is above the min speed and 12Hz still gives 375 points a revolution.
+    speed: Some(lidar::SpeedConfig {
+        speed: 3240,
+        min_speed: 3240,
+        max_speed: 4320,
+        duty: 40.0,
+        p: 0.002,
+        i: 0.001,
+    }),
+    // single stray returns shouldn't stop the rover
+    filters: &[
+        lidar::Filter::Smooth {
+            weight: 0.5,
+            max_jump: 200,
+        },
+        lidar::Filter::Range { min: 50, max: 8000 },
+        lidar::Filter::Isolated {
+            neighbours: 2,
+            max_gap: 100,
+        },
+        lidar::Filter::Median { neighbours: 1 },
+    ],
+};
+
+const TOF_CONFIG: tof::Config = tof::Config {
+    interval: Duration::from_millis(20),
+    max_age: Duration::from_millis(250),
 };
 
 pub struct MotorFactory<'d> {
@@ -69,7 +133,7 @@ impl<'d> MotorFactory<'d> {
         name: &str,
         peripherals: MotorPeripherals<impl Peripheral<P = C> + 'd>,
         invert: bool,
-    ) -> Result<Motor<'d, AnyOutputPin>, EspError>
+    ) -> Result<Motor<'d>, EspError>
     where
         C: LedcChannel,
     {
@@ -101,27 +165,31 @@ where
 }
 
 pub fn speed(
-    motor: Motor<'static, AnyOutputPin>,
+    motor: Motor<'static>,
     encoder: Encoder<'static>,
 ) -> Result<SpeedControl<'static>, SpeedControlError> {
     SpeedControl::new(motor, encoder, SPEED_CONFIG)
 }
 
 pub fn positon(speed: SpeedControl<'static>) -> Result<PositionControl, PositionControlError> {
-    PositionControl::new(speed, POSITION_CONFIG, 720.0)
+    PositionControl::new(speed, POSITION_CONFIG, MAX_WHEEL_SPEED)
 }
 
 pub fn drive(
     left: PositionControl<'static>,
     right: PositionControl<'static>,
-) -> Result<Drive<'static>, std::io::Error> {
+) -> Result<Drive<'static>, DriveError> {
     let left_wheel = Wheel::new(left, WHEEL_DIAMETER);
     let right_wheel = Wheel::new(right, WHEEL_DIAMETER);
-    Drive::new(left_wheel, right_wheel, WHEEL_DISTANCE)
+    Drive::new(left_wheel, right_wheel, DRIVE_CONFIG)
 }
 
-pub fn lidar<UART: Uart>(
-    peripherals: LidarPeripherals<impl Peripheral<P = UART> + 'static>,
+pub fn lidar<UART: Uart, C: LedcChannel, T: LedcTimer>(
+    peripherals: LidarPeripherals<
+        impl Peripheral<P = UART> + 'static,
+        impl Peripheral<P = C> + 'static,
+        impl Peripheral<P = T> + 'static,
+    >,
 ) -> Result<Lidar<'static>, EspError> {
     let config = uart::config::Config::default()
         .baudrate(Hertz(230_400))
@@ -135,5 +203,24 @@ pub fn lidar<UART: Uart>(
         None::<AnyOutputPin>,
         &config,
     )?;
-    Ok(Lidar::new(uart, peripherals.power))
+    // the LD19 takes 20-50kHz on its PWM input
+    let timer_config = ledc::config::TimerConfig::new()
+        .frequency(30.kHz().into())
+        .resolution(Resolution::Bits10);
+    let timer = LedcTimerDriver::new(peripherals.timer, &timer_config)?;
+    let pwm = LedcDriver::new(peripherals.channel, timer, peripherals.pwm)?;
+    Ok(Lidar::with_pwm(uart, peripherals.power, pwm, LIDAR_CONFIG))
+}
+
+// a low front facing sensor for what's below the lidar
+pub fn tof(bus: SharedBus) -> Result<Tof, TofError> {
+    let sensors: Vec<(&'static str, Box<dyn RangeFinder>)> = vec![(
+        "front",
+        Box::new(Vl53l1x::new(
+            bus,
+            tof::VL53L1X_ADDRESS,
+            Duration::from_millis(100),
+        )),
+    )];
+    Tof::new(sensors, TOF_CONFIG)
 }
@@ -8,13 +8,12 @@ use esp_idf_svc::http::server::Configuration;
 use esp_idf_svc::http::server::EspHttpConnection;
 use esp_idf_svc::http::server::EspHttpServer;
 
+use brain::Brain;
+use brain::BrainCmd;
 use log::*;
 use url::ParseError;
 use url::Url;
 
-use crate::brain::Brain;
-use crate::brain::BrainCmd;
-
 const HTML: &str = r#"<!DOCTYPE html>
     <html>
     <head>
@@ -94,7 +93,7 @@ impl HttpController {
             }
         }
         if let Some(value) = state {
-            brain.send(crate::brain::BrainCmd::State(value))?;
+            brain.send(BrainCmd::State(value))?;
             info!("handle_root: sending redirect");
             request.into_response(302, Some("Redirect"), &[("Location", "/")])?;
         } else {
@@ -117,7 +116,7 @@ impl HttpController {
                 state = Self::value_to_bool(v.borrow());
             }
         }
-        brain.send(crate::brain::BrainCmd::State(state))?;
+        brain.send(BrainCmd::State(state))?;
         request.into_ok_response()?;
         Ok(())
     }
@@ -128,6 +127,7 @@ impl HttpController {
     ) -> HandlerResult {
         let mut move_value: Option<i64> = None;
         let mut rotate_value: Option<i64> = None;
+        let mut pivot_value: Option<i64> = None;
         let mut left: bool = false;
         let mut right: bool = false;
         let mut stop = false;
@@ -147,6 +147,12 @@ impl HttpController {
                 } else {
                     rotate_value = Some(v.parse()?);
                 }
+            } else if n == "pivot" {
+                if v == "stop" {
+                    stop = true;
+                } else {
+                    pivot_value = Some(v.parse()?);
+                }
             } else if n == "left" {
                 left = true;
             } else if n == "right" {
@@ -155,7 +161,7 @@ impl HttpController {
         }
         if stop {
             brain.send(BrainCmd::Move(0))?;
-        } else if move_value.is_some() && rotate_value.is_some() {
+        } else if move_value.is_some() && (rotate_value.is_some() || pivot_value.is_some()) {
             result = "Can't move and rotate at the same time!".to_string();
         } else if let Some(value) = move_value {
             if left {
@@ -167,10 +173,9 @@ impl HttpController {
             if !left && !right {
                 brain.send(BrainCmd::Move(value))?;
             }
-        } else if let Some(value) = rotate_value {
-            brain.send(BrainCmd::Rotate(value))?;
-        } else if let Some(value) = rotate_value {
-            brain.send(BrainCmd::Rotate(value))?;
+        } else if let Some(value) = pivot_value {
+            // the single wheel rotate, the rover turns by half of it, see DriveCmd::Pivot
+            brain.send(BrainCmd::Pivot(value))?;
         } else if let Some(value) = rotate_value {
             brain.send(BrainCmd::Rotate(value))?;
         }
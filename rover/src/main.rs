@@ -2,6 +2,9 @@ use std::ffi::CStr;
 
 use embedded_hal::delay::DelayUs;
 use esp_idf_hal::delay::FreeRtos as delay;
+use esp_idf_hal::i2c::I2cConfig;
+use esp_idf_hal::i2c::I2cDriver;
+use esp_idf_hal::units::FromValueType;
 use esp_idf_svc::eventloop::EspSystemEventLoop;
 use esp_idf_svc::netif::IpEvent;
 use esp_idf_svc::wifi::WifiEvent;
@@ -9,9 +12,10 @@ use esp_idf_sys::esp_vfs_spiffs_conf_t;
 use esp_idf_sys::esp_vfs_spiffs_register;
 use esp_idf_sys::{esp, EspError};
 
+use brain::Brain;
+use hal::SharedBus;
 use log::*;
 
-use crate::brain::Brain;
 use crate::config::Config;
 use crate::factory::MotorFactory;
 use crate::http_controller::HttpController;
@@ -19,8 +23,8 @@ use crate::logger::Logger;
 use crate::mqtt::Mqtt;
 use crate::network::Network;
 use crate::peripherals::SystemPeripherals;
+use crate::ranges::Ranges;
 
-mod brain;
 mod config;
 mod factory;
 mod http_controller;
@@ -28,6 +32,7 @@ mod logger;
 mod mqtt;
 mod network;
 mod peripherals;
+mod ranges;
 
 fn log_compile_info() {
     esp_idf_sys::esp_app_desc!();
@@ -96,17 +101,14 @@ fn main() -> anyhow::Result<()> {
     let mqtt = Mqtt::new(&config.mqtt_url, &config.mqtt_user, &config.mqtt_pass)?;
     Logger::set_mqtt(mqtt);
 
-    // info!("setup i2c shared bus");
-    // let i2c_config = I2cConfig::new().baudrate(400.kHz().into());
-    // let i2c = shared_bus::new_std!(
-    //     I2cDriver = I2cDriver::new(
-    //         peripherals.i2c_unit,
-    //         peripherals.i2c_pins.sda,
-    //         peripherals.i2c_pins.scl,
-    //         &i2c_config
-    //     )?
-    // )
-    // .expect("failed to create i2c shared bus");
+    info!("setup i2c shared bus");
+    let i2c_config = I2cConfig::new().baudrate(400.kHz().into());
+    let i2c = SharedBus::new(I2cDriver::new(
+        peripherals.i2c_unit,
+        peripherals.i2c_pins.sda,
+        peripherals.i2c_pins.scl,
+        &i2c_config,
+    )?);
 
     info!("setup motor factory");
     let motor_factory = MotorFactory::new(peripherals.ledc_timer)?;
@@ -114,6 +116,16 @@ fn main() -> anyhow::Result<()> {
     info!("setup lidar");
     let lidar = factory::lidar(peripherals.lidar)?;
 
+    info!("setup tof sensors");
+    // the lidar covers the sectors on its own
+    let tof = match factory::tof(i2c) {
+        Ok(tof) => Some(tof),
+        Err(err) => {
+            error!("tof sensors failed, continuing with the lidar only: {err}");
+            None
+        }
+    };
+
     info!("setup motor/encoder/speed/position: left");
     let motor = motor_factory.motor("left", peripherals.left_motor, false)?;
     let encoder = factory::encoder(peripherals.left_encoder, false)?;
@@ -130,7 +142,7 @@ fn main() -> anyhow::Result<()> {
     let drive = factory::drive(left, right)?;
 
     info!("setup brain");
-    let brain = Brain::new(lidar, drive)?;
+    let brain = Brain::new(Ranges::new(lidar, tof, drive.clone()), drive)?;
 
     let _http = HttpController::new(brain)?;
     info!("server is up!");
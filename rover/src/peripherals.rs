@@ -4,14 +4,16 @@ use esp_idf_hal::gpio::AnyOutputPin;
 use esp_idf_hal::i2c::I2C0;
 use esp_idf_hal::ledc::CHANNEL0;
 use esp_idf_hal::ledc::CHANNEL1;
+use esp_idf_hal::ledc::CHANNEL2;
 use esp_idf_hal::ledc::TIMER0;
+use esp_idf_hal::ledc::TIMER1;
 use esp_idf_hal::modem::Modem;
 use esp_idf_hal::pcnt::PCNT0;
 use esp_idf_hal::pcnt::PCNT1;
 use esp_idf_hal::prelude::Peripherals;
 use esp_idf_hal::uart::UART1;
 
-pub struct SystemPeripherals<C0, C1, T, I, P0, P1, U> {
+pub struct SystemPeripherals<C0, C1, C2, T, T1, I, P0, P1, U> {
     pub i2c_unit: I,
     pub i2c_pins: I2cPeripherals,
     pub ledc_timer: T,
@@ -19,11 +21,11 @@ pub struct SystemPeripherals<C0, C1, T, I, P0, P1, U> {
     pub left_encoder: EncoderPeripherals<P0>,
     pub right_motor: MotorPeripherals<C1>,
     pub right_encoder: EncoderPeripherals<P1>,
-    pub lidar: LidarPeripherals<U>,
+    pub lidar: LidarPeripherals<U, C2, T1>,
     pub modem: Modem,
 }
 
-impl SystemPeripherals<CHANNEL0, CHANNEL1, TIMER0, I2C0, PCNT0, PCNT1, UART1> {
+impl SystemPeripherals<CHANNEL0, CHANNEL1, CHANNEL2, TIMER0, TIMER1, I2C0, PCNT0, PCNT1, UART1> {
     pub fn take() -> Self {
         let peripherals = Peripherals::take().unwrap();
 
@@ -58,6 +60,9 @@ impl SystemPeripherals<CHANNEL0, CHANNEL1, TIMER0, I2C0, PCNT0, PCNT1, UART1> {
                 uart: peripherals.uart1,
                 serial: peripherals.pins.gpio1.into(),
                 power: peripherals.pins.gpio2.into(),
+                channel: peripherals.ledc.channel2,
+                timer: peripherals.ledc.timer1,
+                pwm: peripherals.pins.gpio4.into(),
             },
             modem: peripherals.modem,
         }
@@ -76,10 +81,14 @@ pub struct EncoderPeripherals<P> {
     pub b: AnyInputPin,
 }
 
-pub struct LidarPeripherals<U> {
+// the speed PWM needs its own timer, the motors' runs at 50Hz
+pub struct LidarPeripherals<U, C, T> {
     pub uart: U,
     pub serial: AnyInputPin,
     pub power: AnyOutputPin,
+    pub channel: C,
+    pub timer: T,
+    pub pwm: AnyOutputPin,
 }
 
 pub struct I2cPeripherals {
@@ -0,0 +1,86 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use differential_drive::odometry::normalize;
+use differential_drive::Drive;
+use differential_drive::PoseHistory;
+use lidar::Frame;
+use lidar::Lidar;
+use lidar::Pose;
+use lidar::PowerStatus;
+use lidar::RangeScanner;
+use lidar::Sector;
+use tof::Tof;
+
+use crate::factory::TOP_SPEED;
+use crate::factory::TOP_TURN_RATE;
+
+// how far back the rover's motion is measured for the lidar speed
+const MOTION_WINDOW: Duration = Duration::from_millis(200);
+
+// The lidar with the ToF sensors, if they started, filling in. A sensor named like a sector lowers
+// that sector's range when it sees something closer, like an obstacle below the lidar. The lidar
+// points are de-skewed with the drive's odometry to where the rover is now, and the lidar spins
+// faster the faster the rover moves.
+pub struct Ranges {
+    lidar: Lidar<'static>,
+    tof: Option<Tof>,
+    drive: Drive<'static>,
+}
+
+impl Ranges {
+    pub fn new(lidar: Lidar<'static>, tof: Option<Tof>, drive: Drive<'static>) -> Self {
+        Self { lidar, tof, drive }
+    }
+}
+
+// 0 standing still to 1 flat out, driving or turning
+fn motion(history: &PoseHistory, now: Instant) -> Option<f32> {
+    let from = history.at(now - MOTION_WINDOW)?;
+    let to = history.at(now)?;
+    let seconds = MOTION_WINDOW.as_secs_f64();
+    let speed = (to.x - from.x).hypot(to.y - from.y) / seconds;
+    let turn_rate = normalize(to.heading - from.heading).abs() / seconds;
+    Some((speed / TOP_SPEED).max(turn_rate / TOP_TURN_RATE) as f32)
+}
+
+fn pose(pose: differential_drive::Pose) -> Pose {
+    Pose {
+        x: pose.x as f32,
+        y: pose.y as f32,
+        heading: pose.heading as f32,
+    }
+}
+
+impl RangeScanner for Ranges {
+    fn set_power(&mut self, value: bool) {
+        self.lidar.set_power(value)
+    }
+
+    fn get_frame(&self, sectors: &[Sector]) -> Frame {
+        let history = self.drive.pose_history();
+        let now = Instant::now();
+        if let Some(motion) = motion(&history, now) {
+            self.lidar.set_motion(motion);
+        }
+        let lidar = self
+            .lidar
+            .deskewed_frame(sectors, now, |time| history.at(time).map(pose));
+        let ranges = lidar
+            .ranges()
+            .iter()
+            .map(|(name, range)| {
+                let tof = self.tof.as_ref().and_then(|tof| tof.range(name));
+                match (*range, tof) {
+                    (Some(lidar), Some(tof)) => (*name, Some(lidar.min(tof))),
+                    (lidar, tof) => (*name, lidar.or(tof)),
+                }
+            })
+            .collect();
+        Frame::new(ranges)
+    }
+
+    fn power_status(&self) -> &PowerStatus {
+        self.lidar.power_status()
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use esp_idf_hal::delay::FreeRtos as delay;
+use esp_idf_hal::i2c::I2cConfig;
+use esp_idf_hal::i2c::I2cDriver;
+use esp_idf_hal::ledc::config;
+use esp_idf_hal::ledc::LedcTimerDriver;
+use esp_idf_hal::ledc::Resolution;
+use esp_idf_hal::prelude::*;
+
+use anyhow::Context;
+use embedded_hal::delay::DelayUs;
+use log::*;
+
+use encoder::Encoder;
+use hal::SharedBus;
+use motor::Motor;
+use scanner::Scanner;
+use speed_control::SpeedControl;
+use tof::TfLuna;
+
+const TICKS_PER_REVOLUTION: u32 = 1440;
+
+const SPEED_CONFIG: speed_control::Config = speed_control::Config {
+    interval: Duration::from_millis(100),
+    p: 0.5,
+    i: 0.1,
+    d: 0.2,
+};
+
+// one revolution a second with the TF-Luna at 250Hz, about 250 points a revolution
+const SCANNER_CONFIG: scanner::Config = scanner::Config {
+    speed: 360.0,
+    offset: 0.0,
+    latency: Duration::from_millis(2),
+    interval: Duration::from_millis(2),
+    lidar: lidar::Config {
+        resolution: 2.0,
+        min_confidence: 0,
+        max_age: Duration::from_millis(1500),
+        sectors: lidar::DEFAULT_SECTORS,
+        mount: lidar::Mount::CENTERED,
+        mask: &[],
+        health: lidar::Health {
+            frame_timeout: Duration::from_millis(100),
+            min_speed: 300,
+            min_points: 200,
+        },
+        // the head's speed is under speed_control
+        speed: None,
+        // a revolution a second is slow to refresh, no smoothing
+        filters: &[lidar::Filter::Range {
+            min: 100,
+            max: 8000,
+        }],
+    },
+};
+
+fn main() -> anyhow::Result<()> {
+    esp_idf_sys::link_patches();
+    // Bind the log crate to the ESP Logging facilities
+    esp_idf_svc::log::EspLogger::initialize_default();
+    info!("starting");
+    let peripherals = Peripherals::take().context("failed to take Peripherals")?;
+    // scanner motor pins
+    let pin_m3_en = peripherals.pins.gpio46;
+    let pin_m3_ph = peripherals.pins.gpio3;
+    // scanner encoder pins
+    let pin_m3_enc1 = peripherals.pins.gpio11;
+    let pin_m3_enc2 = peripherals.pins.gpio12;
+
+    let config = config::TimerConfig::new()
+        .frequency(50.Hz())
+        .resolution(Resolution::Bits10);
+    let timer_driver =
+        LedcTimerDriver::new(peripherals.ledc.timer0, &config).context("creating timer driver")?;
+
+    let motor = Motor::new(
+        "scanner",
+        peripherals.ledc.channel0,
+        &timer_driver,
+        pin_m3_ph,
+        pin_m3_en,
+        true,
+    )
+    .context("creating scanner motor")?;
+
+    let encoder = Encoder::new(
+        peripherals.pcnt0,
+        pin_m3_enc1,
+        pin_m3_enc2,
+        TICKS_PER_REVOLUTION,
+        true,
+    )
+    .context("create scanner encoder")?;
+
+    let speed = SpeedControl::new(motor, encoder, SPEED_CONFIG)?;
+
+    let i2c_config = I2cConfig::new().baudrate(400.kHz().into());
+    let i2c = I2cDriver::new(
+        peripherals.i2c0,
+        peripherals.pins.gpio21,
+        peripherals.pins.gpio47,
+        &i2c_config,
+    )
+    .context("create i2c driver")?;
+    // through the slip ring
+    let luna = TfLuna::new(SharedBus::new(i2c), tof::TFLUNA_ADDRESS, 250);
+
+    info!("create scanner");
+    let scanner = Scanner::new(speed, luna, SCANNER_CONFIG)?;
+    loop {
+        info!("spin the scanner up");
+        scanner.set_power(true);
+        while scanner.power_status().state() != lidar::PowerState::Synced {
+            delay.delay_ms(100);
+        }
+
+        for _ in 0..20 {
+            let frame = scanner.frame(lidar::DEFAULT_SECTORS);
+            info!(
+                "{:?} points: {}",
+                frame.ranges(),
+                scanner.latest_scan().len()
+            );
+            delay.delay_ms(1000);
+        }
+
+        info!("stop the scanner");
+        scanner.set_power(false);
+        delay.delay_ms(10000);
+    }
+}
@@ -0,0 +1,300 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use lidar::Data;
+use lidar::Frame;
+use lidar::Packet;
+use lidar::PowerStatus;
+use lidar::Published;
+use lidar::RangeScanner;
+use lidar::Sample;
+use lidar::Scan;
+use lidar::Sector;
+use log::*;
+use speed_control::SpeedControl;
+use speed_control::SpeedControlCmd;
+use tof::RangeFinder;
+use tof::TofError;
+
+// the encoder counts whole degrees, the rotation rate is measured over this long
+const RATE_WINDOW: Duration = Duration::from_millis(100);
+
+// readers see new points at least this often, and after every revolution
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub speed: f32,           // degrees per second the head spins at
+    pub offset: f32,          // degrees, the encoder position where the sensor faces forward
+    pub latency: Duration,    // from the middle of a measurement until it can be read
+    pub interval: Duration,   // between sensor polls, shorter than the sensor's measurement period
+    pub lidar: lidar::Config, // the scan as for the LD19, the health limits for this scanner
+}
+
+// Where the head pointed for a reading. The encoder position trails the sensor by the latency,
+// the head kept turning at the measured rate since the middle of the measurement.
+#[derive(Debug)]
+struct Head {
+    speed: f32, // the rate until it's measured
+    offset: f32,
+    latency: Duration,
+    last: Option<(Instant, i64)>,
+    rate: f32, // degrees per second
+}
+
+impl Head {
+    fn new(config: &Config) -> Self {
+        Self {
+            speed: config.speed,
+            offset: config.offset,
+            latency: config.latency,
+            last: None,
+            rate: config.speed,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.rate = self.speed;
+    }
+
+    // degrees 0..360 from the front and the time at the middle of the measurement read at `now`
+    fn measured(&mut self, position: i64, now: Instant) -> (f32, Instant) {
+        match self.last {
+            Some((time, last_position)) if now - time >= RATE_WINDOW => {
+                self.rate = (position - last_position) as f32 / (now - time).as_secs_f32();
+                self.last = Some((now, position));
+            }
+            None => self.last = Some((now, position)),
+            _ => {}
+        }
+        let angle = position as f32 + self.offset - self.rate * self.latency.as_secs_f32();
+        (angle.rem_euclid(360.0), now - self.latency)
+    }
+}
+
+// A ToF sensor on a spinning head, the motor and encoder under speed control. The encoder is
+// expected to count up turning clockwise seen from above, one revolution of its position is one
+// of the head. Readings go into `lidar::Data` like the LD19's points so the brain takes either,
+// the scanner's thread owns the data and readers get the published snapshots.
+pub struct Scanner {
+    published: Arc<Published>,
+    running: Arc<AtomicBool>,
+    status: PowerStatus,
+}
+
+impl Scanner {
+    pub fn new(
+        speed: SpeedControl<'static>,
+        mut sensor: impl RangeFinder + 'static,
+        config: Config,
+    ) -> Result<Self, ScannerError> {
+        sensor.start()?;
+        let published = Arc::new(Published::new(config.lidar));
+        let running = Arc::new(AtomicBool::new(false));
+        let status = PowerStatus::new();
+        {
+            let published = published.clone();
+            let running = running.clone();
+            let status = status.clone();
+            thread::Builder::new()
+                .stack_size(4096)
+                .name("scanner".into())
+                .spawn(move || {
+                    let mut data = Data::new(config.lidar);
+                    let mut head = Head::new(&config);
+                    let mut powered = false;
+                    let mut last_published = Instant::now();
+                    loop {
+                        let on = running.load(Ordering::SeqCst);
+                        if on != powered {
+                            powered = on;
+                            status.power(on);
+                            let target = if on { config.speed } else { 0.0 };
+                            if let Err(err) = speed.send(SpeedControlCmd::SetSpeed(target)) {
+                                warn!("scanner speed: {err:?}");
+                            }
+                            if !on {
+                                data.reset();
+                                published.publish(data.snapshot());
+                            }
+                            head.reset();
+                        }
+                        if !on {
+                            // wait for the scanner to be enabled
+                            thread::sleep(Duration::from_millis(100));
+                            continue;
+                        }
+                        let mut revolution = false;
+                        match sensor.read() {
+                            Ok(Some(reading)) => {
+                                let (angle, measured) =
+                                    head.measured(speed.get_position(), Instant::now());
+                                let packet = Packet {
+                                    // measured from the revolutions
+                                    speed: 0,
+                                    samples: vec![Sample {
+                                        angle: (angle * 100.0) as u16 % 36000,
+                                        distance: reading.distance,
+                                        // the drivers already drop weak returns
+                                        intensity: u8::MAX,
+                                    }],
+                                };
+                                revolution = data.update(&packet, measured);
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                warn!("scanner error: {err:?}");
+                                data.add_error();
+                            }
+                        }
+                        if revolution || last_published.elapsed() >= PUBLISH_INTERVAL {
+                            published.publish(data.snapshot());
+                            last_published = Instant::now();
+                        }
+                        status.update(data.is_healthy());
+                        thread::sleep(config.interval);
+                    }
+                })?;
+        }
+        Ok(Self {
+            published,
+            running,
+            status,
+        })
+    }
+
+    pub fn set_power(&self, value: bool) {
+        self.running.store(value, Ordering::SeqCst);
+    }
+
+    pub fn latest_scan(&self) -> Scan {
+        self.published.read().scan()
+    }
+
+    pub fn frame(&self, sectors: &[Sector]) -> Frame {
+        self.published.read().frame(sectors)
+    }
+
+    pub fn power_status(&self) -> &PowerStatus {
+        &self.status
+    }
+}
+
+impl RangeScanner for Scanner {
+    fn set_power(&mut self, value: bool) {
+        Scanner::set_power(self, value)
+    }
+
+    fn get_frame(&self, sectors: &[Sector]) -> Frame {
+        Scanner::frame(self, sectors)
+    }
+
+    fn power_status(&self) -> &PowerStatus {
+        Scanner::power_status(self)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScannerError {
+    TofError(TofError),
+    IOError(std::io::Error),
+}
+
+impl std::fmt::Display for ScannerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for ScannerError {}
+
+impl From<TofError> for ScannerError {
+    fn from(e: TofError) -> Self {
+        ScannerError::TofError(e)
+    }
+}
+
+impl From<std::io::Error> for ScannerError {
+    fn from(e: std::io::Error) -> Self {
+        ScannerError::IOError(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: Config = Config {
+        speed: 360.0,
+        offset: 90.0,
+        latency: Duration::from_millis(10),
+        interval: Duration::from_millis(2),
+        lidar: lidar::Config::DEFAULT,
+    };
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn compensates_the_latency_at_the_configured_speed() {
+        let mut head = Head::new(&CONFIG);
+        let now = Instant::now();
+        let (angle, measured) = head.measured(100, now);
+        // 360 degrees a second for 10ms is 3.6 degrees back
+        assert_near(angle, 100.0 + 90.0 - 3.6);
+        assert_eq!(measured, now - CONFIG.latency);
+    }
+
+    #[test]
+    fn measures_the_rate() {
+        let mut head = Head::new(&CONFIG);
+        let start = Instant::now();
+        head.measured(0, start);
+        // too soon to measure, still the configured speed
+        let (angle, _) = head.measured(36, start + Duration::from_millis(50));
+        assert_near(angle, 36.0 + 90.0 - 3.6);
+        // half speed over the window, 1.8 degrees in 10ms
+        let (angle, _) = head.measured(54, start + Duration::from_millis(300));
+        assert_near(angle, 54.0 + 90.0 - 1.8);
+        // carries on at that rate until the next window
+        let (angle, _) = head.measured(60, start + Duration::from_millis(350));
+        assert_near(angle, 60.0 + 90.0 - 1.8);
+    }
+
+    #[test]
+    fn angle_wraps() {
+        let mut head = Head::new(&CONFIG);
+        let now = Instant::now();
+        let (angle, _) = head.measured(358, now);
+        assert_near(angle, 88.0 - 3.6);
+        // counts keep going past a revolution, and backwards below 0
+        let (angle, _) = head.measured(720 + 270, now);
+        assert_near(angle, 356.4);
+        let mut head = Head::new(&CONFIG);
+        let (angle, _) = head.measured(-100, now);
+        assert_near(angle, 350.0 - 3.6);
+    }
+
+    #[test]
+    fn reset_forgets_the_rate() {
+        let mut head = Head::new(&CONFIG);
+        let start = Instant::now();
+        head.measured(0, start);
+        head.measured(0, start + Duration::from_millis(200));
+        let (angle, _) = head.measured(0, start + Duration::from_millis(210));
+        // standing still, nothing to compensate
+        assert_near(angle, 90.0);
+        head.reset();
+        let (angle, _) = head.measured(0, start + Duration::from_millis(220));
+        assert_near(angle, 90.0 - 3.6);
+    }
+}
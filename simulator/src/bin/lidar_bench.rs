@@ -0,0 +1,148 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+
+use hal::host::SimPin;
+use hal::host::SimSerial;
+use lidar::Lidar;
+use lidar::Replay;
+use lidar_ld19::LidarFrame;
+use lidar_ld19::PACKET_SIZE;
+
+// 230400 baud, 10 bits a byte
+const BYTES_PER_SECOND: usize = 23040;
+const CHUNK: Duration = Duration::from_millis(1);
+const WARMUP: Duration = Duration::from_secs(1); // measure once the stream is steady
+
+// Measures the CPU time the lidar receive thread uses for a byte stream fed at the LD19's baud
+// rate, the stream is encoded from a lidar log or made up of a wall 1m away all around.
+// usage: lidar_bench [seconds] [lidar-log]
+fn main() -> anyhow::Result<()> {
+    let mut args = env::args().skip(1);
+    let seconds: u64 = match args.next() {
+        Some(value) => value.parse().context("seconds must be a number")?,
+        None => 20,
+    };
+    let stream = match args.next() {
+        Some(log) => {
+            let mut stream = Vec::new();
+            for record in Replay::new(BufReader::new(File::open(&log)?))? {
+                let logged = record?.packet;
+                // as LD19 packets, 12 samples at a time
+                for samples in logged.samples.chunks_exact(12) {
+                    let points: Vec<(u16, u8)> =
+                        samples.iter().map(|s| (s.distance, s.intensity)).collect();
+                    stream.extend(packet(
+                        logged.speed,
+                        samples[0].angle,
+                        &points,
+                        samples[11].angle,
+                        0,
+                    ));
+                }
+            }
+            stream
+        }
+        None => wall(1000),
+    };
+    anyhow::ensure!(!stream.is_empty(), "empty byte stream");
+
+    let (tx, serial) = SimSerial::channel();
+    let mut lidar = Lidar::with_driver(serial, SimPin::new(), lidar::Config::default());
+    lidar.set_power_on();
+
+    let chunk = BYTES_PER_SECOND * CHUNK.as_micros() as usize / 1_000_000;
+    let start = Instant::now();
+    let mut measured = None;
+    let mut sent = 0usize;
+    while start.elapsed() < WARMUP + Duration::from_secs(seconds) {
+        if measured.is_none() && start.elapsed() >= WARMUP {
+            measured = Some((Instant::now(), thread_cpu("lidar")?));
+        }
+        let bytes = (0..chunk)
+            .map(|i| stream[(sent + i) % stream.len()])
+            .collect();
+        sent += chunk;
+        tx.send(bytes)?;
+        thread::sleep(CHUNK);
+    }
+    let (since, cpu) = measured.context("no measurement")?;
+    let cpu = thread_cpu("lidar")? - cpu;
+    let wall = since.elapsed();
+    let stats = lidar.stats();
+    println!(
+        "lidar thread: {:.2}s cpu in {:.2}s ({:.1}%), {} frames, {} errors",
+        cpu.as_secs_f64(),
+        wall.as_secs_f64(),
+        cpu.as_secs_f64() / wall.as_secs_f64() * 100.0,
+        stats.frames,
+        stats.errors
+    );
+    Ok(())
+}
+
+// user + system time of the named thread, linux only
+fn thread_cpu(name: &str) -> anyhow::Result<Duration> {
+    for task in fs::read_dir("/proc/self/task")? {
+        let path = task?.path();
+        if fs::read_to_string(path.join("comm"))?.trim() != name {
+            continue;
+        }
+        let stat = fs::read_to_string(path.join("stat"))?;
+        // the fields after the parenthesised name, utime and stime are the 14th and 15th
+        let fields: Vec<&str> = stat
+            .rsplit_once(')')
+            .context("bad stat")?
+            .1
+            .split_whitespace()
+            .collect();
+        let ticks: u64 = fields[11].parse::<u64>()? + fields[12].parse::<u64>()?;
+        // clock ticks are 100Hz on linux
+        return Ok(Duration::from_millis(ticks * 10));
+    }
+    anyhow::bail!("no {name} thread")
+}
+
+// one revolution at 10Hz
+fn wall(distance: u16) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for n in 0..38u16 {
+        let start_angle = n * 960 % 36000;
+        let end_angle = (start_angle + 880) % 36000;
+        stream.extend(packet(
+            3600,
+            start_angle,
+            &[(distance, 200); 12],
+            end_angle,
+            n,
+        ));
+    }
+    stream
+}
+
+fn packet(
+    speed: u16,
+    start_angle: u16,
+    points: &[(u16, u8)],
+    end_angle: u16,
+    timestamp: u16,
+) -> [u8; PACKET_SIZE] {
+    let mut frame = LidarFrame {
+        speed,
+        start_angle,
+        end_angle,
+        timestamp,
+        ..LidarFrame::default()
+    };
+    for (point, (distance, confidence)) in frame.points.iter_mut().zip(points) {
+        point.distance = *distance;
+        point.confidence = *confidence;
+    }
+    frame.to_bytes()
+}
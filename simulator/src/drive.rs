@@ -0,0 +1,125 @@
+use std::sync::mpsc::SendError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use brain::DriveControl;
+use differential_drive::kinematics;
+use differential_drive::CommandId;
+use differential_drive::DriveCmd;
+use differential_drive::Notifier;
+use differential_drive::Status;
+use log::*;
+
+use crate::plant::Plant;
+
+// Simulated `differential_drive::Drive`, translates drive commands into wheel targets on the plant.
+#[derive(Clone)]
+pub struct SimDrive {
+    plant: Arc<Mutex<Plant>>,
+    velocity_timeout: Duration,
+    notifier: Notifier,
+}
+
+impl SimDrive {
+    pub fn new(plant: Arc<Mutex<Plant>>, velocity_timeout: Duration) -> Self {
+        let notifier = plant.lock().unwrap().notifier().clone();
+        Self {
+            plant,
+            velocity_timeout,
+            notifier,
+        }
+    }
+}
+
+impl DriveControl for SimDrive {
+    fn send(&self, cmd: DriveCmd) -> Result<CommandId, SendError<DriveCmd>> {
+        let id = CommandId::next();
+        let mut plant = self.plant.lock().unwrap();
+        // same completions as the drive, a velocity is completed by the next one
+        let replaced = match (plant.in_velocity(), cmd) {
+            (true, DriveCmd::Velocity { .. }) => Status::Completed,
+            _ => Status::Cancelled,
+        };
+        plant.set_command(id, replaced);
+        let wheel_dist = plant.geometry().wheel_distance;
+        let (left, right) = plant.get_positions();
+        if !matches!(cmd, DriveCmd::Velocity { .. }) {
+            plant.clear_velocities();
+        }
+        if !matches!(cmd, DriveCmd::Drive(_) | DriveCmd::Stop) {
+            plant.set_max_speeds(None, None);
+        }
+        match cmd {
+            DriveCmd::Move(distance) => {
+                info!("Move {distance}");
+                plant.set_left_target(left + distance as f64);
+                plant.set_right_target(right + distance as f64);
+            }
+            DriveCmd::Rotate(degrees) => {
+                info!("Rotate {degrees}");
+                let (l, r) = kinematics::spin(degrees as f64, wheel_dist);
+                plant.set_left_target(left + l);
+                plant.set_right_target(right + r);
+            }
+            DriveCmd::Pivot(degrees) => {
+                info!("Pivot {degrees}");
+                let (l, r) = kinematics::pivot(degrees as f64, wheel_dist);
+                if degrees > 0 {
+                    plant.set_left_target(left + l);
+                } else {
+                    plant.set_right_target(right + r);
+                }
+            }
+            DriveCmd::Drive((translational, angular, distance)) => {
+                info!("Drive {translational} {angular} {distance}");
+                let (l, r) = kinematics::arc(
+                    translational as f64,
+                    angular as f64,
+                    distance as f64,
+                    wheel_dist,
+                    plant.geometry().max_speed,
+                );
+                plant.set_max_speeds(Some(l.speed), Some(r.speed));
+                if l.distance.abs() >= 1.0 {
+                    plant.set_left_target(left + l.distance);
+                }
+                if r.distance.abs() >= 1.0 {
+                    plant.set_right_target(right + r.distance);
+                }
+            }
+            DriveCmd::Left(distance) => {
+                info!("LeftWheel {distance}");
+                plant.set_left_target(left + distance as f64);
+            }
+            DriveCmd::Right(distance) => {
+                info!("RightWheel {distance}");
+                plant.set_right_target(right + distance as f64);
+            }
+            DriveCmd::Velocity {
+                linear_mm_s,
+                angular_deg_s,
+            } => {
+                debug!("Velocity {linear_mm_s} {angular_deg_s}");
+                let (l, r) =
+                    kinematics::wheel_speeds(linear_mm_s as f64, angular_deg_s as f64, wheel_dist);
+                plant.set_velocities(l, r, self.velocity_timeout);
+            }
+            DriveCmd::Stop => {
+                plant.stop();
+            }
+        }
+        if !plant.is_active() {
+            plant.finish_command(Status::Completed);
+        }
+        Ok(id)
+    }
+
+    fn is_active(&self) -> bool {
+        self.plant.lock().unwrap().is_active()
+    }
+
+    fn notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+}
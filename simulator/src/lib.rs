@@ -0,0 +1,14 @@
+pub mod drive;
+pub mod lidar;
+pub mod plant;
+pub mod replay;
+pub mod world;
+
+pub use crate::drive::SimDrive;
+pub use crate::lidar::SimLidar;
+pub use crate::plant::Geometry;
+pub use crate::plant::Plant;
+pub use crate::plant::Pose;
+pub use crate::replay::ReplayLidar;
+pub use crate::world::World;
+pub use crate::world::WorldError;
@@ -0,0 +1,140 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use lidar::Data;
+use lidar::Frame;
+use lidar::PowerStatus;
+use lidar::RangeScanner;
+use lidar::Scan;
+use lidar::ScanPoint;
+use lidar::Sector;
+
+use crate::plant::Plant;
+use crate::world::World;
+
+// LD19: 10 revolutions per second at 4500 samples per second, 12m range.
+const SAMPLES_PER_SECOND: f64 = 4500.0;
+const DEGREES_PER_SECOND: f64 = 3600.0;
+const MAX_RANGE: f64 = 12000.0;
+const INTERVAL: Duration = Duration::from_millis(10);
+
+// Simulated LD19, casts rays from the plant pose into the world and feeds the samples to
+// `lidar::Data` in the same angle convention as the real sensor (clockwise, 0 is straight ahead).
+pub struct SimLidar {
+    data: Arc<Mutex<Data>>,
+    running: Arc<AtomicBool>,
+    status: PowerStatus,
+}
+
+impl SimLidar {
+    pub fn new(
+        world: Arc<World>,
+        plant: Arc<Mutex<Plant>>,
+        config: lidar::Config,
+    ) -> Result<Self, std::io::Error> {
+        let data = Arc::new(Mutex::new(Data::new(config)));
+        let mount = config.mount;
+        let running = Arc::new(AtomicBool::new(false));
+        let status = PowerStatus::new();
+        {
+            let data = data.clone();
+            let running = running.clone();
+            let status = status.clone();
+            thread::Builder::new()
+                .name("sim-lidar".into())
+                .spawn(move || {
+                    let mut noise = Noise::new(0x5eed);
+                    let mut angle = 0.0f64;
+                    let samples = (SAMPLES_PER_SECOND * INTERVAL.as_secs_f64()) as usize;
+                    let step = DEGREES_PER_SECOND / SAMPLES_PER_SECOND;
+                    let mut swept = 0.0;
+                    loop {
+                        thread::sleep(INTERVAL);
+                        if !running.load(Ordering::SeqCst) {
+                            swept = 0.0;
+                            continue;
+                        }
+                        let pose = plant.lock().unwrap().pose();
+                        // the sensor sits at the mount offset, its angles turned by the mount
+                        let (sin, cos) = pose.heading.sin_cos();
+                        let x = pose.x + mount.x as f64 * cos - mount.y as f64 * sin;
+                        let y = pose.y + mount.x as f64 * sin + mount.y as f64 * cos;
+                        let time = Instant::now();
+                        let mut data = data.lock().unwrap();
+                        for _ in 0..samples {
+                            let direction =
+                                pose.heading - (mount.angle(angle as f32) as f64).to_radians();
+                            let (distance, intensity) =
+                                match world.ray_cast(x, y, direction, MAX_RANGE) {
+                                    // ~1% range noise, weaker returns further away
+                                    Some(d) => (
+                                        (d * (1.0 + noise.next() * 0.01)).clamp(0.0, MAX_RANGE),
+                                        (255.0 - d / MAX_RANGE * 200.0) as u8,
+                                    ),
+                                    // no return reads as 0 like the LD19
+                                    None => (0.0, 0),
+                                };
+                            data.add_point(ScanPoint {
+                                angle: angle as f32,
+                                distance: distance as u16,
+                                intensity,
+                                time,
+                            });
+                            angle = (angle + step) % 360.0;
+                        }
+                        // always at speed, synced after the first revolution
+                        swept += samples as f64 * step;
+                        status.update(swept >= 360.0);
+                    }
+                })?;
+        }
+        Ok(Self {
+            data,
+            running,
+            status,
+        })
+    }
+
+    pub fn latest_scan(&self) -> Scan {
+        self.data.lock().unwrap().scan()
+    }
+}
+
+impl RangeScanner for SimLidar {
+    fn set_power(&mut self, value: bool) {
+        self.running.store(value, Ordering::SeqCst);
+        if !value {
+            self.data.lock().unwrap().reset();
+        }
+        self.status.power(value);
+    }
+
+    fn get_frame(&self, sectors: &[Sector]) -> Frame {
+        self.data.lock().unwrap().frame(sectors)
+    }
+
+    fn power_status(&self) -> &PowerStatus {
+        &self.status
+    }
+}
+
+// small xorshift so the simulator doesn't need a rng dependency, returns values in -1.0..1.0
+struct Noise(u64);
+
+impl Noise {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+}
@@ -0,0 +1,97 @@
+use std::env;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use log::*;
+
+use brain::Brain;
+use brain::BrainCmd;
+use simulator::Geometry;
+use simulator::Plant;
+use simulator::ReplayLidar;
+use simulator::SimDrive;
+use simulator::SimLidar;
+use simulator::World;
+
+const PLANT_INTERVAL: Duration = Duration::from_millis(10);
+const VELOCITY_TIMEOUT: Duration = Duration::from_millis(500); // matches rover::factory
+
+static LOGGER: Logger = Logger;
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            println!(
+                "{} {} {}",
+                record.level(),
+                record.metadata().target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// usage: simulator <world-file> [seconds] [lidar-log]
+// with a lidar log the brain sees the recorded frames instead of the simulated lidar
+fn main() -> anyhow::Result<ExitCode> {
+    set_logger(&LOGGER).unwrap();
+    set_max_level(LevelFilter::Info);
+
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .context("usage: simulator <world-file> [seconds] [lidar-log]")?;
+    let seconds: u64 = match args.next() {
+        Some(value) => value.parse().context("seconds must be a number")?,
+        None => 60,
+    };
+    let log = args.next();
+
+    let world = Arc::new(World::load(&path)?);
+    info!("loaded {path}: {} segments", world.segments().len());
+
+    let plant = Arc::new(Mutex::new(Plant::new(Geometry::default(), world.clone())));
+    Plant::run(plant.clone(), PLANT_INTERVAL)?;
+
+    let drive = SimDrive::new(plant.clone(), VELOCITY_TIMEOUT);
+    let brain = match log {
+        Some(log) => Brain::new(ReplayLidar::new(&log, lidar::Config::default())?, drive)?,
+        None => Brain::new(
+            SimLidar::new(world, plant.clone(), lidar::Config::default())?,
+            drive,
+        )?,
+    };
+    brain.send(BrainCmd::State(true))?;
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(seconds) {
+        thread::sleep(Duration::from_secs(1));
+        let plant = plant.lock().unwrap();
+        let pose = plant.pose();
+        info!(
+            "pose: x={:.0} y={:.0} heading={:.0}",
+            pose.x,
+            pose.y,
+            pose.heading.to_degrees().rem_euclid(360.0)
+        );
+        if plant.collided() {
+            error!("rover collided with the world");
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+    brain.send(BrainCmd::State(false))?;
+    Ok(ExitCode::SUCCESS)
+}
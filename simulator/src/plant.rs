@@ -0,0 +1,323 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use differential_drive::CommandId;
+use differential_drive::Notifier;
+use differential_drive::Status;
+use log::*;
+
+use crate::world::World;
+
+// Rover geometry, the defaults match `rover::factory`.
+#[derive(Debug, Clone, Copy)]
+pub struct Geometry {
+    pub wheel_diameter: f64, // mm
+    pub wheel_distance: f64, // mm between the wheels
+    pub radius: f64,         // mm, used for collisions
+    pub max_speed: f64,      // mm/s at the wheel
+    pub acceleration: f64,   // mm/s/s at the wheel
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        let wheel_diameter = 60.0;
+        Self {
+            wheel_diameter,
+            wheel_distance: 100.0,
+            radius: 75.0,
+            // position control limits the wheels to 720 degrees per second
+            max_speed: 720.0 / 360.0 * wheel_diameter * PI,
+            // and accelerates them at 1440 degrees per second per second
+            acceleration: 1440.0 / 360.0 * wheel_diameter * PI,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pose {
+    pub x: f64,       // mm
+    pub y: f64,       // mm
+    pub heading: f64, // radians, counter clockwise from +x
+}
+
+impl Pose {
+    pub fn new(x: f64, y: f64, heading: f64) -> Self {
+        Self { x, y, heading }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SimWheel {
+    position: f64, // mm travelled
+    speed: f64,    // mm/s
+    target: Option<f64>,
+    max_speed: Option<f64>,
+    velocity: Option<f64>, // mm/s, used when there is no target
+}
+
+impl SimWheel {
+    fn step(&mut self, dt: f64, max_speed: f64, acceleration: f64) -> f64 {
+        let max_speed = self.max_speed.unwrap_or(max_speed);
+        let wanted = match self.target {
+            Some(target) => {
+                let remaining = target - self.position;
+                if remaining.abs() < 1.0 && self.speed.abs() < acceleration * dt {
+                    self.target = None;
+                    0.0
+                } else {
+                    // slow down in time to stop at the target
+                    let stopping = (2.0 * acceleration * remaining.abs()).sqrt();
+                    remaining.signum() * stopping.min(max_speed)
+                }
+            }
+            None => self.velocity.unwrap_or(0.0).clamp(-max_speed, max_speed),
+        };
+        let change = (wanted - self.speed).clamp(-acceleration * dt, acceleration * dt);
+        self.speed += change;
+        let delta = self.speed * dt;
+        self.position += delta;
+        delta
+    }
+}
+
+// Differential drive plant, wheels are driven towards position targets (in mm) like
+// `wheel::Wheel` and the pose is integrated from the wheel movement.
+#[derive(Debug)]
+pub struct Plant {
+    geometry: Geometry,
+    world: Arc<World>,
+    pose: Pose,
+    left: SimWheel,
+    right: SimWheel,
+    velocity_deadline: Option<Instant>,
+    command: Option<CommandId>,
+    notifier: Notifier,
+    collided: bool,
+}
+
+impl Plant {
+    pub fn new(geometry: Geometry, world: Arc<World>) -> Self {
+        Self {
+            geometry,
+            pose: world.start(),
+            world,
+            left: SimWheel::default(),
+            right: SimWheel::default(),
+            velocity_deadline: None,
+            command: None,
+            notifier: Notifier::new(),
+            collided: false,
+        }
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    pub fn notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    // the command that the wheels are moving for, the one it replaces is finished with `replaced`
+    pub fn set_command(&mut self, id: CommandId, replaced: Status) {
+        if let Some(previous) = self.command.replace(id) {
+            self.notifier.notify(previous, replaced);
+        }
+    }
+
+    pub fn finish_command(&mut self, status: Status) {
+        if let Some(id) = self.command.take() {
+            self.notifier.notify(id, status);
+        }
+    }
+
+    pub fn in_velocity(&self) -> bool {
+        self.velocity_deadline.is_some()
+    }
+
+    pub fn collided(&self) -> bool {
+        self.collided
+    }
+
+    pub fn get_positions(&self) -> (f64, f64) {
+        (self.left.position, self.right.position)
+    }
+
+    pub fn set_left_target(&mut self, target: f64) {
+        self.left.target = Some(target);
+    }
+
+    pub fn set_right_target(&mut self, target: f64) {
+        self.right.target = Some(target);
+    }
+
+    // mm/s for each wheel, None is the geometry max speed
+    pub fn set_max_speeds(&mut self, left: Option<f64>, right: Option<f64>) {
+        self.left.max_speed = left;
+        self.right.max_speed = right;
+    }
+
+    // mm/s for each wheel until the next target, stop or the timeout
+    pub fn set_velocities(&mut self, left: f64, right: f64, timeout: Duration) {
+        self.left.velocity = Some(left);
+        self.right.velocity = Some(right);
+        self.velocity_deadline = Some(Instant::now() + timeout);
+    }
+
+    pub fn clear_velocities(&mut self) {
+        self.velocity_deadline = None;
+        self.left.velocity = None;
+        self.right.velocity = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.left.target = None;
+        self.right.target = None;
+        self.clear_velocities();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.left.target.is_some()
+            || self.right.target.is_some()
+            || self.left.velocity.is_some()
+            || self.right.velocity.is_some()
+    }
+
+    pub fn step(&mut self, dt: f64) {
+        if self.velocity_deadline.is_some_and(|d| Instant::now() >= d) {
+            warn!("velocity timed out, stopping");
+            self.clear_velocities();
+            self.finish_command(Status::Cancelled);
+        }
+        let g = self.geometry;
+        let left = self.left.step(dt, g.max_speed, g.acceleration);
+        let right = self.right.step(dt, g.max_speed, g.acceleration);
+        let distance = (left + right) / 2.0;
+        let rotation = (right - left) / g.wheel_distance;
+        let heading = self.pose.heading + rotation / 2.0;
+        let x = self.pose.x + distance * heading.cos();
+        let y = self.pose.y + distance * heading.sin();
+        if self.world.collides(x, y, g.radius) {
+            if !self.collided {
+                warn!("collision at x={x:.0} y={y:.0}");
+            }
+            self.collided = true;
+            self.left.speed = 0.0;
+            self.right.speed = 0.0;
+            self.pose.heading += rotation;
+            // the wheels can't reach their targets, give up like position control does
+            if self.command.is_some() {
+                self.stop();
+                self.finish_command(Status::Failed);
+            }
+        } else {
+            self.pose = Pose::new(x, y, self.pose.heading + rotation);
+        }
+        if !self.is_active() {
+            self.finish_command(Status::Completed);
+        }
+    }
+
+    // step the plant in real time from a background thread
+    pub fn run(plant: Arc<Mutex<Plant>>, interval: Duration) -> Result<(), std::io::Error> {
+        thread::Builder::new().name("plant".into()).spawn(move || {
+            let mut last = Instant::now();
+            loop {
+                thread::sleep(interval);
+                let now = Instant::now();
+                plant
+                    .lock()
+                    .unwrap()
+                    .step(now.duration_since(last).as_secs_f64());
+                last = now;
+            }
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Receiver;
+
+    use differential_drive::Completion;
+
+    use super::*;
+
+    const DT: f64 = 0.01;
+
+    fn plant(world: &str) -> (Plant, CommandId, Receiver<Completion>) {
+        let world = Arc::new(World::parse(world).unwrap());
+        let mut plant = Plant::new(Geometry::default(), world);
+        let completions = plant.notifier().subscribe();
+        let id = CommandId::next();
+        plant.set_command(id, Status::Cancelled);
+        (plant, id, completions)
+    }
+
+    #[test]
+    fn reaches_the_target() {
+        let (mut plant, id, completions) = plant("");
+        plant.set_left_target(500.0);
+        plant.set_right_target(500.0);
+        for _ in 0..500 {
+            plant.step(DT);
+        }
+        let pose = plant.pose();
+        assert!((pose.x - 500.0).abs() < 1.0, "{pose:?}");
+        assert!(pose.y.abs() < 1e-9);
+        assert_eq!(
+            completions.try_recv(),
+            Ok(Completion {
+                id,
+                status: Status::Completed
+            })
+        );
+        assert!(!plant.collided());
+    }
+
+    #[test]
+    fn spins_in_place() {
+        let (mut plant, _, _) = plant("");
+        // a quarter turn counter clockwise, the wheels are 100mm apart
+        let quarter = 100.0 * PI / 4.0;
+        plant.set_left_target(-quarter);
+        plant.set_right_target(quarter);
+        for _ in 0..500 {
+            plant.step(DT);
+        }
+        let pose = plant.pose();
+        assert!((pose.heading - PI / 2.0).abs() < 0.02, "{pose:?}");
+        assert!(pose.x.abs() < 1e-9 && pose.y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn fails_on_a_collision() {
+        let (mut plant, id, completions) = plant("wall 300 -500 300 500");
+        plant.set_left_target(1000.0);
+        plant.set_right_target(1000.0);
+        for _ in 0..500 {
+            plant.step(DT);
+        }
+        assert!(plant.collided());
+        assert!(!plant.is_active());
+        // stopped short of the wall by the rover's radius
+        let pose = plant.pose();
+        assert!(pose.x < 300.0 - 75.0 && pose.x > 200.0, "{pose:?}");
+        assert_eq!(
+            completions.try_recv(),
+            Ok(Completion {
+                id,
+                status: Status::Failed
+            })
+        );
+    }
+}
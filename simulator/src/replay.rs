@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use lidar::Data;
+use lidar::Frame;
+use lidar::PowerStatus;
+use lidar::RangeScanner;
+use lidar::Record;
+use lidar::Replay;
+use lidar::Scan;
+use lidar::Sector;
+use log::*;
+
+// Plays a log written by `lidar::Lidar::start_recording` back through `lidar::Data` at the pace
+// it was recorded, starting when the brain powers the lidar on. The plant doesn't affect what it
+// sees.
+pub struct ReplayLidar {
+    data: Arc<Mutex<Data>>,
+    running: Arc<AtomicBool>,
+    status: PowerStatus,
+}
+
+impl ReplayLidar {
+    pub fn new(path: &str, config: lidar::Config) -> Result<Self, std::io::Error> {
+        let records =
+            Replay::new(BufReader::new(File::open(path)?))?.collect::<Result<Vec<Record>, _>>()?;
+        info!("loaded {path}: {} packets", records.len());
+        let data = Arc::new(Mutex::new(Data::new(config)));
+        let running = Arc::new(AtomicBool::new(false));
+        let status = PowerStatus::new();
+        {
+            let data = data.clone();
+            let running = running.clone();
+            let status = status.clone();
+            thread::Builder::new()
+                .name("replay-lidar".into())
+                .spawn(move || {
+                    let mut records = records.into_iter().peekable();
+                    let mut start: Option<(Instant, Duration)> = None;
+                    loop {
+                        if !running.load(Ordering::SeqCst) {
+                            start = None;
+                            thread::sleep(Duration::from_millis(10));
+                            continue;
+                        }
+                        match records.next() {
+                            Some(record) => {
+                                // pauses while the lidar is off are skipped
+                                let (started, offset) =
+                                    *start.get_or_insert((Instant::now(), record.time));
+                                let due = started + record.time.saturating_sub(offset);
+                                thread::sleep(due.saturating_duration_since(Instant::now()));
+                                data.lock().unwrap().update(&record.packet, Instant::now());
+                                if records.peek().is_none() {
+                                    info!("replay finished");
+                                }
+                            }
+                            // the sensor looks stalled after the end of the log
+                            None => thread::sleep(Duration::from_millis(10)),
+                        }
+                        status.update(data.lock().unwrap().is_healthy());
+                    }
+                })?;
+        }
+        Ok(Self {
+            data,
+            running,
+            status,
+        })
+    }
+
+    pub fn latest_scan(&self) -> Scan {
+        self.data.lock().unwrap().scan()
+    }
+}
+
+impl RangeScanner for ReplayLidar {
+    fn set_power(&mut self, value: bool) {
+        self.running.store(value, Ordering::SeqCst);
+        if !value {
+            self.data.lock().unwrap().reset();
+        }
+        self.status.power(value);
+    }
+
+    fn get_frame(&self, sectors: &[Sector]) -> Frame {
+        self.data.lock().unwrap().frame(sectors)
+    }
+
+    fn power_status(&self) -> &PowerStatus {
+        &self.status
+    }
+}
@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::Path;
+
+use crate::plant::Pose;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Segment {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+impl Segment {
+    pub fn new(x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        Self { x1, y1, x2, y2 }
+    }
+
+    // distance along the ray (x, y) + t * (dx, dy) to this segment, (dx, dy) must be a unit vector
+    fn intersect(&self, x: f64, y: f64, dx: f64, dy: f64) -> Option<f64> {
+        let sx = self.x2 - self.x1;
+        let sy = self.y2 - self.y1;
+        let denom = dx * sy - dy * sx;
+        if denom.abs() < f64::EPSILON {
+            return None;
+        }
+        let ox = self.x1 - x;
+        let oy = self.y1 - y;
+        let t = (ox * sy - oy * sx) / denom;
+        let u = (ox * dy - oy * dx) / denom;
+        if t >= 0.0 && (0.0..=1.0).contains(&u) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    fn distance_to(&self, x: f64, y: f64) -> f64 {
+        let sx = self.x2 - self.x1;
+        let sy = self.y2 - self.y1;
+        let len2 = sx * sx + sy * sy;
+        let u = match len2 {
+            l if l > 0.0 => (((x - self.x1) * sx + (y - self.y1) * sy) / l).clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+        let px = self.x1 + u * sx;
+        let py = self.y1 + u * sy;
+        ((x - px).powi(2) + (y - py).powi(2)).sqrt()
+    }
+}
+
+// A 2D world made of line segments, all units are mm and degrees.
+//
+// World files have one item per line, `#` starts a comment:
+//
+//   start <x> <y> <heading>           initial rover pose (heading counter clockwise from +x)
+//   wall <x1> <y1> <x2> <y2>          a single wall segment
+//   box <x> <y> <width> <height>      a rectangular obstacle with its lower left corner at x, y
+#[derive(Debug, Clone, Default)]
+pub struct World {
+    segments: Vec<Segment>,
+    start: Pose,
+}
+
+impl World {
+    pub fn new(segments: Vec<Segment>, start: Pose) -> Self {
+        Self { segments, start }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WorldError> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, WorldError> {
+        let mut world = World::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(pos) => &line[..pos],
+                None => line,
+            };
+            let mut words = line.split_whitespace();
+            let kind = match words.next() {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let values = words
+                .map(|w| w.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| WorldError::Parse(index + 1, line.to_string()))?;
+            match (kind, values.as_slice()) {
+                ("start", &[x, y, heading]) => {
+                    world.start = Pose::new(x, y, heading.to_radians());
+                }
+                ("wall", &[x1, y1, x2, y2]) => {
+                    world.segments.push(Segment::new(x1, y1, x2, y2));
+                }
+                ("box", &[x, y, w, h]) => {
+                    world.segments.push(Segment::new(x, y, x + w, y));
+                    world.segments.push(Segment::new(x + w, y, x + w, y + h));
+                    world.segments.push(Segment::new(x + w, y + h, x, y + h));
+                    world.segments.push(Segment::new(x, y + h, x, y));
+                }
+                _ => return Err(WorldError::Parse(index + 1, line.to_string())),
+            }
+        }
+        Ok(world)
+    }
+
+    pub fn start(&self) -> Pose {
+        self.start
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    // distance to the closest segment hit by a ray from (x, y) in direction `angle` (radians)
+    pub fn ray_cast(&self, x: f64, y: f64, angle: f64, max_range: f64) -> Option<f64> {
+        let (dy, dx) = angle.sin_cos();
+        self.segments
+            .iter()
+            .filter_map(|s| s.intersect(x, y, dx, dy))
+            .filter(|d| *d <= max_range)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    // true if a circle of `radius` at (x, y) touches any segment
+    pub fn collides(&self, x: f64, y: f64, radius: f64) -> bool {
+        self.segments.iter().any(|s| s.distance_to(x, y) < radius)
+    }
+}
+
+#[derive(Debug)]
+pub enum WorldError {
+    IOError(std::io::Error),
+    Parse(usize, String),
+}
+
+impl std::fmt::Display for WorldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for WorldError {}
+
+impl From<std::io::Error> for WorldError {
+    fn from(e: std::io::Error) -> Self {
+        WorldError::IOError(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOM: &str = "
+# a comment line
+start 500 250 90 # trailing comment
+
+wall 0 0 1000 0
+box 200 600 100 50
+";
+
+    #[test]
+    fn parse() {
+        let world = World::parse(ROOM).unwrap();
+        assert_eq!(world.start(), Pose::new(500.0, 250.0, 90f64.to_radians()));
+        // the wall and the four sides of the box
+        assert_eq!(world.segments().len(), 5);
+        let side = world.segments()[2];
+        assert_eq!(
+            (side.x1, side.y1, side.x2, side.y2),
+            (300.0, 600.0, 300.0, 650.0)
+        );
+    }
+
+    #[test]
+    fn parse_errors_give_the_line() {
+        for (text, line) in [
+            ("wall 0 0 1000 0\nwall 0 0 x 0", 2),
+            ("# header\n\nbox 0 0 10", 3),
+            ("door 0 0 10 10", 1),
+        ] {
+            match World::parse(text) {
+                Err(WorldError::Parse(number, _)) => assert_eq!(number, line, "{text}"),
+                other => panic!("{text}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn ray_cast() {
+        let world = World::parse("wall 1000 -500 1000 500\nwall 2000 -500 2000 500").unwrap();
+        let hit = world.ray_cast(0.0, 0.0, 0.0, 5000.0).unwrap();
+        assert!((hit - 1000.0).abs() < 1e-9);
+        // 45 degrees up hits the nearer wall at its end
+        let hit = world
+            .ray_cast(500.0, 0.0, 45f64.to_radians(), 5000.0)
+            .unwrap();
+        assert!((hit - 500.0 * 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(world.ray_cast(0.0, 0.0, 0.0, 900.0), None);
+        assert_eq!(world.ray_cast(0.0, 0.0, 180f64.to_radians(), 5000.0), None);
+        assert_eq!(world.ray_cast(0.0, 0.0, 90f64.to_radians(), 5000.0), None);
+    }
+
+    #[test]
+    fn collides() {
+        let world = World::parse("box 0 0 1000 1000").unwrap();
+        assert!(!world.collides(500.0, 500.0, 75.0));
+        assert!(world.collides(950.0, 500.0, 75.0));
+        // past the corner
+        assert!(world.collides(1050.0, 1050.0, 75.0));
+        assert!(!world.collides(1100.0, 1100.0, 75.0));
+    }
+}
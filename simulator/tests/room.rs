@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use brain::Brain;
+use brain::BrainCmd;
+use simulator::Geometry;
+use simulator::Plant;
+use simulator::SimDrive;
+use simulator::SimLidar;
+use simulator::World;
+
+const PLANT_INTERVAL: Duration = Duration::from_millis(10);
+const VELOCITY_TIMEOUT: Duration = Duration::from_millis(500);
+const RUN: Duration = Duration::from_secs(10);
+
+// The brain drives around the example room without hitting anything.
+#[test]
+fn room() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/worlds/room.txt");
+    let world = Arc::new(World::load(path).unwrap());
+    let start = world.start();
+    let plant = Arc::new(Mutex::new(Plant::new(Geometry::default(), world.clone())));
+    Plant::run(plant.clone(), PLANT_INTERVAL).unwrap();
+    let drive = SimDrive::new(plant.clone(), VELOCITY_TIMEOUT);
+    let lidar = SimLidar::new(world, plant.clone(), lidar::Config::default()).unwrap();
+    let brain = Brain::new(lidar, drive).unwrap();
+    brain.send(BrainCmd::State(true)).unwrap();
+
+    let started = Instant::now();
+    while started.elapsed() < RUN {
+        thread::sleep(Duration::from_millis(100));
+        assert!(!plant.lock().unwrap().collided(), "collided");
+    }
+    brain.send(BrainCmd::State(false)).unwrap();
+    // and it went somewhere
+    let pose = plant.lock().unwrap().pose();
+    let moved = (pose.x - start.x).hypot(pose.y - start.y);
+    assert!(moved > 100.0, "{pose:?}");
+}
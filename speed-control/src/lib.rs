@@ -1,19 +1,12 @@
-use std::ptr;
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::SendError;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
 
-use esp_idf_hal::gpio::OutputPin;
-use esp_idf_svc::timer::EspTaskTimerService;
-use esp_idf_sys::vTaskPrioritySet;
-use esp_idf_sys::EspError;
-use esp_idf_sys::TaskHandle_t;
-use esp_idf_sys::ESP_TASK_PRIO_MAX;
-
+use hal::HalError;
+use hal::Priority;
 use log::*;
 use pid::Pid;
 
@@ -46,7 +39,7 @@ const MILLIS_PER_SEC: u64 = 1_000;
 
 impl<'d> SpeedControl<'d> {
     pub fn new(
-        mut motor: Motor<'static, impl OutputPin>,
+        mut motor: Motor<'static>,
         encoder: Encoder<'static>,
         config: Config,
     ) -> Result<Self, SpeedControlError> {
@@ -61,10 +54,8 @@ impl<'d> SpeedControl<'d> {
             let tx = tx.clone();
             let name = name.clone();
             let encoder = encoder.clone();
-            let timer = EspTaskTimerService::new()?
-                .timer(move || tx.send(SpeedControlCmd::Tick(0)).unwrap())?;
-            thread::Builder::new().stack_size(4096).spawn(move || {
-                unsafe { vTaskPrioritySet(ptr::null_mut() as TaskHandle_t, ESP_TASK_PRIO_MAX - 2) }
+            let timer = hal::timer(move || tx.send(SpeedControlCmd::Tick(0)).unwrap())?;
+            hal::spawn("speed", 4096, Priority::High, move || {
                 let mut last_position = 0i64;
                 loop {
                     match rx.recv().unwrap() {
@@ -127,7 +118,7 @@ impl<'d> SpeedControl<'d> {
 
 #[derive(Debug)]
 pub enum SpeedControlError {
-    EspError(EspError),
+    HalError(HalError),
     IOError(std::io::Error),
 }
 
@@ -139,9 +130,9 @@ impl std::fmt::Display for SpeedControlError {
 
 impl std::error::Error for SpeedControlError {}
 
-impl From<EspError> for SpeedControlError {
-    fn from(e: EspError) -> Self {
-        SpeedControlError::EspError(e)
+impl From<HalError> for SpeedControlError {
+    fn from(e: HalError) -> Self {
+        SpeedControlError::HalError(e)
     }
 }
 
@@ -150,3 +141,94 @@ impl From<std::io::Error> for SpeedControlError {
         SpeedControlError::IOError(e)
     }
 }
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use hal::host::SimCounter;
+    use hal::host::SimPin;
+    use hal::host::SimPwm;
+
+    use super::*;
+
+    const TICKS_PER_REVOLUTION: u32 = 1440;
+    const FULL_SPEED: f32 = 1000.0; // degrees per second at 100%
+
+    const CONFIG: Config = Config {
+        interval: Duration::from_millis(10),
+        p: 2.0,
+        i: 1.0,
+        d: 0.0,
+    };
+
+    // A motor whose speed follows the duty, turning the encoder, and what it's driven with.
+    fn plant() -> (Motor<'static>, Encoder<'static>, SimPwm) {
+        let phase = SimPin::new();
+        let pwm = SimPwm::new(1000);
+        let counter = SimCounter::new();
+        let motor = Motor::with_driver("test", phase.clone(), pwm.clone(), false);
+        let encoder = Encoder::with_counter(counter.clone(), TICKS_PER_REVOLUTION, false);
+        {
+            let pwm = pwm.clone();
+            thread::spawn(move || {
+                let ticks_per_degree = TICKS_PER_REVOLUTION as f32 / 360.0;
+                let mut last = Instant::now();
+                let mut ticks = 0.0f32;
+                loop {
+                    thread::sleep(Duration::from_millis(1));
+                    let now = Instant::now();
+                    let direction = if phase.is_high() { -1.0 } else { 1.0 };
+                    let speed = direction * pwm.get_percent() / 100.0 * FULL_SPEED;
+                    let before = ticks as i64;
+                    ticks += speed * ticks_per_degree * (now - last).as_secs_f32();
+                    counter.add(ticks as i64 - before);
+                    last = now;
+                }
+            });
+        }
+        (motor, encoder, pwm)
+    }
+
+    // degrees per second over `period`
+    fn measure(speed: &SpeedControl, period: Duration) -> f32 {
+        let start = speed.get_position();
+        thread::sleep(period);
+        (speed.get_position() - start) as f32 / period.as_secs_f32()
+    }
+
+    #[test]
+    fn holds_the_set_speed() {
+        let (motor, encoder, _) = plant();
+        let speed = SpeedControl::new(motor, encoder, CONFIG).unwrap();
+        speed.send(SpeedControlCmd::SetSpeed(360.0)).unwrap();
+        thread::sleep(Duration::from_secs(1));
+        let measured = measure(&speed, Duration::from_secs(1));
+        assert!((measured - 360.0).abs() < 36.0, "{measured} degrees/s");
+    }
+
+    #[test]
+    fn runs_backwards() {
+        let (motor, encoder, _) = plant();
+        let speed = SpeedControl::new(motor, encoder, CONFIG).unwrap();
+        speed.send(SpeedControlCmd::SetSpeed(-180.0)).unwrap();
+        thread::sleep(Duration::from_secs(1));
+        let measured = measure(&speed, Duration::from_secs(1));
+        assert!((measured + 180.0).abs() < 18.0, "{measured} degrees/s");
+    }
+
+    #[test]
+    fn zero_stops_the_motor() {
+        let (motor, encoder, pwm) = plant();
+        let speed = SpeedControl::new(motor, encoder, CONFIG).unwrap();
+        speed.send(SpeedControlCmd::SetSpeed(360.0)).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        assert!(pwm.get_duty() > 0);
+        speed.send(SpeedControlCmd::SetSpeed(0.0)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(pwm.get_duty(), 0);
+        assert_eq!(measure(&speed, Duration::from_millis(200)), 0.0);
+    }
+}
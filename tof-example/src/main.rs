@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use esp_idf_hal::delay::FreeRtos as delay;
+use esp_idf_hal::i2c::I2cConfig;
+use esp_idf_hal::i2c::I2cDriver;
+use esp_idf_hal::prelude::*;
+
+use anyhow::Context;
+use embedded_hal::delay::DelayUs;
+use log::*;
+
+use hal::SharedBus;
+use tof::RangeFinder;
+use tof::TfLuna;
+use tof::Tof;
+use tof::Vl53l1x;
+
+const TOF_CONFIG: tof::Config = tof::Config {
+    interval: Duration::from_millis(20),
+    max_age: Duration::from_millis(250),
+};
+
+fn main() -> anyhow::Result<()> {
+    esp_idf_sys::link_patches();
+    // Bind the log crate to the ESP Logging facilities
+    esp_idf_svc::log::EspLogger::initialize_default();
+    info!("starting");
+    let peripherals = Peripherals::take().context("failed to take Peripherals")?;
+
+    let i2c_config = I2cConfig::new().baudrate(400.kHz().into());
+    let i2c = I2cDriver::new(
+        peripherals.i2c0,
+        peripherals.pins.gpio21,
+        peripherals.pins.gpio47,
+        &i2c_config,
+    )
+    .context("create i2c driver")?;
+    let bus = SharedBus::new(i2c);
+
+    let mut luna = TfLuna::new(bus.clone(), tof::TFLUNA_ADDRESS, 50);
+    info!("tf-luna version: {:?}", luna.version()?);
+    let sensors: Vec<(&'static str, Box<dyn RangeFinder>)> = vec![
+        ("luna", Box::new(luna)),
+        (
+            "vl53l1x",
+            Box::new(Vl53l1x::new(
+                bus,
+                tof::VL53L1X_ADDRESS,
+                Duration::from_millis(100),
+            )),
+        ),
+    ];
+    let tof = Tof::new(sensors, TOF_CONFIG).context("start sensors")?;
+
+    loop {
+        info!("{:?}", tof.ranges());
+        delay.delay_ms(500);
+    }
+}
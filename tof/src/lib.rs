@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use hal::HalError;
+use log::*;
+
+mod tfluna;
+mod vl53l1x;
+
+pub use tfluna::TfLuna;
+pub use tfluna::TFLUNA_ADDRESS;
+pub use vl53l1x::Vl53l1x;
+pub use vl53l1x::VL53L1X_ADDRESS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+    pub distance: u16, // mm, 0 when there was no usable return
+    pub strength: u16, // sensor specific signal strength
+}
+
+// Single point range sensor, `start` sets it measuring continuously.
+pub trait RangeFinder: Send {
+    fn start(&mut self) -> Result<(), TofError>;
+    // None until a new measurement is available
+    fn read(&mut self) -> Result<Option<Reading>, TofError>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub interval: Duration, // between polls of all the sensors
+    pub max_age: Duration,  // older readings are ignored
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Latest {
+    distance: u16,
+    time: Instant,
+}
+
+// Fixed-direction range sensors, each named like a lidar sector, polled on their own thread. A
+// sensor that doesn't start is left out, its sector has no range as if it saw nothing.
+pub struct Tof {
+    names: Vec<&'static str>,
+    latest: Arc<Mutex<Vec<Option<Latest>>>>,
+    max_age: Duration,
+}
+
+impl Tof {
+    pub fn new(
+        sensors: Vec<(&'static str, Box<dyn RangeFinder>)>,
+        config: Config,
+    ) -> Result<Self, TofError> {
+        let mut sensors: Vec<_> = sensors
+            .into_iter()
+            .filter_map(|(name, mut sensor)| {
+                info!("starting {name}");
+                match sensor.start() {
+                    Ok(()) => Some((name, sensor)),
+                    Err(err) => {
+                        error!("{name} failed to start, continuing without it: {err:?}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        let names: Vec<&'static str> = sensors.iter().map(|(name, _)| *name).collect();
+        let latest = Arc::new(Mutex::new(vec![None; sensors.len()]));
+        {
+            let latest = latest.clone();
+            thread::Builder::new()
+                .stack_size(4096)
+                .name("tof".into())
+                .spawn(move || loop {
+                    for (i, (name, sensor)) in sensors.iter_mut().enumerate() {
+                        match sensor.read() {
+                            Ok(Some(reading)) => {
+                                latest.lock().unwrap()[i] = Some(Latest {
+                                    distance: reading.distance,
+                                    time: Instant::now(),
+                                });
+                            }
+                            Ok(None) => {}
+                            Err(err) => warn!("{name}: {err:?}"),
+                        }
+                    }
+                    thread::sleep(config.interval);
+                })?;
+        }
+        Ok(Self {
+            names,
+            latest,
+            max_age: config.max_age,
+        })
+    }
+
+    // mm, None when the sensor has no recent return
+    pub fn range(&self, name: &str) -> Option<u16> {
+        let index = self.names.iter().position(|n| *n == name)?;
+        self.get(index)
+    }
+
+    // in the shape of `lidar::Frame::new`
+    pub fn ranges(&self) -> Vec<(&'static str, Option<u16>)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, self.get(i)))
+            .collect()
+    }
+
+    fn get(&self, index: usize) -> Option<u16> {
+        let latest = self.latest.lock().unwrap()[index]?;
+        (latest.distance > 0 && latest.time.elapsed() <= self.max_age).then_some(latest.distance)
+    }
+}
+
+#[derive(Debug)]
+pub enum TofError {
+    HalError(HalError),
+    IOError(std::io::Error),
+    WrongDevice(u16), // the id the device reported
+    Timeout,
+}
+
+impl std::fmt::Display for TofError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl std::error::Error for TofError {}
+
+impl From<HalError> for TofError {
+    fn from(e: HalError) -> Self {
+        TofError::HalError(e)
+    }
+}
+
+impl From<std::io::Error> for TofError {
+    fn from(e: std::io::Error) -> Self {
+        TofError::IOError(e)
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use hal::host::SimI2c;
+    use hal::SharedBus;
+
+    use super::*;
+
+    const CONFIG: Config = Config {
+        interval: Duration::from_millis(5),
+        max_age: Duration::from_millis(100),
+    };
+
+    // TF-Luna with a measurement of `cm` and tick 1
+    fn luna(bus: &SimI2c, address: u8, cm: u16) {
+        bus.add_device(address, 1);
+        let mut registers = cm.to_le_bytes().to_vec();
+        registers.extend([0xf4, 0x01, 0x00, 0x00, 0x01, 0x00]);
+        bus.set_registers(address, 0, &registers);
+    }
+
+    fn sensor(bus: &SimI2c, address: u8) -> Box<dyn RangeFinder> {
+        Box::new(TfLuna::new(SharedBus::new(bus.clone()), address, 100))
+    }
+
+    #[test]
+    fn ranges_by_name() {
+        let bus = SimI2c::new();
+        luna(&bus, 0x10, 50);
+        luna(&bus, 0x11, 80);
+        let tof = Tof::new(
+            vec![("front", sensor(&bus, 0x10)), ("left", sensor(&bus, 0x11))],
+            CONFIG,
+        )
+        .unwrap();
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(tof.range("front"), Some(500));
+        assert_eq!(tof.range("left"), Some(800));
+        assert_eq!(tof.range("right"), None);
+        assert_eq!(tof.ranges(), [("front", Some(500)), ("left", Some(800))]);
+    }
+
+    #[test]
+    fn old_readings_expire() {
+        let bus = SimI2c::new();
+        luna(&bus, 0x10, 50);
+        let tof = Tof::new(vec![("front", sensor(&bus, 0x10))], CONFIG).unwrap();
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(tof.range("front"), Some(500));
+        // the tick doesn't move, no new measurement
+        thread::sleep(CONFIG.max_age);
+        assert_eq!(tof.range("front"), None);
+    }
+
+    #[test]
+    fn sensor_that_fails_to_start_is_left_out() {
+        let bus = SimI2c::new();
+        luna(&bus, 0x10, 50);
+        // nothing at 0x11
+        let tof = Tof::new(
+            vec![("front", sensor(&bus, 0x11)), ("left", sensor(&bus, 0x10))],
+            CONFIG,
+        )
+        .unwrap();
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(tof.ranges(), [("left", Some(500))]);
+        assert_eq!(tof.range("front"), None);
+    }
+}
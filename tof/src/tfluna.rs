@@ -0,0 +1,159 @@
+use hal::I2cBus;
+
+use crate::RangeFinder;
+use crate::Reading;
+use crate::TofError;
+
+pub const TFLUNA_ADDRESS: u8 = 0x10;
+
+// registers, 16 bit values are little endian
+const DIST: u8 = 0x00; // cm, followed by AMP, TEMP and TICK
+const VERSION: u8 = 0x0a;
+const MODE: u8 = 0x23; // 0 continuous, 1 triggered
+const ENABLE: u8 = 0x25;
+const FPS: u8 = 0x26;
+
+// below this (or at 0xffff, overexposed) the distance isn't reliable
+const MIN_AMP: u16 = 100;
+
+// Benewake TF-Luna in I2C mode (pin 5 tied to ground at power up).
+pub struct TfLuna<B: I2cBus> {
+    bus: B,
+    address: u8,
+    fps: u16,
+    tick: Option<u16>,
+}
+
+impl<B: I2cBus> TfLuna<B> {
+    // `fps` is the measurement rate, 1..=250
+    pub fn new(bus: B, address: u8, fps: u16) -> Self {
+        Self {
+            bus,
+            address,
+            fps,
+            tick: None,
+        }
+    }
+
+    // major, minor, revision
+    pub fn version(&mut self) -> Result<[u8; 3], TofError> {
+        let mut version = [0u8; 3];
+        self.read_registers(VERSION, &mut version)?;
+        Ok([version[2], version[1], version[0]])
+    }
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), TofError> {
+        Ok(self.bus.write_read(self.address, &[register], buffer)?)
+    }
+
+    fn write_register(&mut self, register: u8, bytes: &[u8]) -> Result<(), TofError> {
+        let mut buffer = vec![register];
+        buffer.extend_from_slice(bytes);
+        Ok(self.bus.write(self.address, &buffer)?)
+    }
+}
+
+impl<B: I2cBus> RangeFinder for TfLuna<B> {
+    fn start(&mut self) -> Result<(), TofError> {
+        self.write_register(MODE, &[0])?;
+        self.write_register(FPS, &self.fps.to_le_bytes())?;
+        self.write_register(ENABLE, &[1])?;
+        self.tick = None;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Option<Reading>, TofError> {
+        let mut buffer = [0u8; 8];
+        self.read_registers(DIST, &mut buffer)?;
+        let u16_at = |i: usize| u16::from_le_bytes([buffer[i], buffer[i + 1]]);
+        // the tick only changes with a new measurement
+        let tick = u16_at(6);
+        if self.tick.replace(tick) == Some(tick) {
+            return Ok(None);
+        }
+        let amp = u16_at(2);
+        let distance = match amp {
+            MIN_AMP..=0xfffe => u16_at(0).saturating_mul(10),
+            _ => 0,
+        };
+        Ok(Some(Reading {
+            distance,
+            strength: amp,
+        }))
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use hal::host::SimI2c;
+
+    use super::*;
+
+    fn luna() -> (SimI2c, TfLuna<SimI2c>) {
+        let bus = SimI2c::new();
+        bus.add_device(TFLUNA_ADDRESS, 1);
+        let luna = TfLuna::new(bus.clone(), TFLUNA_ADDRESS, 100);
+        (bus, luna)
+    }
+
+    // distance in cm, amplitude, temperature and tick
+    fn measure(bus: &SimI2c, cm: u16, amp: u16, tick: u16) {
+        let mut registers = cm.to_le_bytes().to_vec();
+        registers.extend(amp.to_le_bytes());
+        registers.extend(2500u16.to_le_bytes());
+        registers.extend(tick.to_le_bytes());
+        bus.set_registers(TFLUNA_ADDRESS, DIST as u16, &registers);
+    }
+
+    #[test]
+    fn start_sets_continuous_mode() {
+        let (bus, mut luna) = luna();
+        bus.set_registers(TFLUNA_ADDRESS, MODE as u16, &[1]);
+        luna.start().unwrap();
+        assert_eq!(bus.registers(TFLUNA_ADDRESS, MODE as u16, 1), [0]);
+        assert_eq!(bus.registers(TFLUNA_ADDRESS, ENABLE as u16, 1), [1]);
+        assert_eq!(bus.registers(TFLUNA_ADDRESS, FPS as u16, 2), [100, 0]);
+    }
+
+    #[test]
+    fn reads_new_measurements() {
+        let (bus, mut luna) = luna();
+        luna.start().unwrap();
+        measure(&bus, 123, 500, 1);
+        let reading = luna.read().unwrap();
+        assert_eq!(
+            reading,
+            Some(Reading {
+                distance: 1230,
+                strength: 500,
+            })
+        );
+        // same tick, same measurement
+        assert_eq!(luna.read().unwrap(), None);
+        measure(&bus, 124, 500, 2);
+        assert_eq!(luna.read().unwrap().unwrap().distance, 1240);
+    }
+
+    #[test]
+    fn unreliable_amplitude_is_no_return() {
+        let (bus, mut luna) = luna();
+        luna.start().unwrap();
+        measure(&bus, 123, MIN_AMP - 1, 1);
+        assert_eq!(luna.read().unwrap().unwrap().distance, 0);
+        measure(&bus, 123, 0xffff, 2);
+        assert_eq!(luna.read().unwrap().unwrap().distance, 0);
+    }
+
+    #[test]
+    fn version() {
+        let (bus, mut luna) = luna();
+        bus.set_registers(TFLUNA_ADDRESS, VERSION as u16, &[3, 2, 1]);
+        assert_eq!(luna.version().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn missing_device_is_an_error() {
+        let mut luna = TfLuna::new(SimI2c::new(), TFLUNA_ADDRESS, 100);
+        assert!(matches!(luna.start(), Err(TofError::HalError(_))));
+    }
+}
@@ -0,0 +1,231 @@
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use hal::I2cBus;
+
+use crate::RangeFinder;
+use crate::Reading;
+use crate::TofError;
+
+pub const VL53L1X_ADDRESS: u8 = 0x29;
+
+// registers have 16 bit addresses, values are big endian
+const VHV_CONFIG_TIMEOUT_MACROP_LOOP_BOUND: u16 = 0x0008;
+const VHV_CONFIG_INIT: u16 = 0x000b;
+const DEFAULT_CONFIGURATION_START: u16 = 0x002d;
+const GPIO_TIO_HV_STATUS: u16 = 0x0031;
+const SYSTEM_INTERMEASUREMENT_PERIOD: u16 = 0x006c;
+const SYSTEM_INTERRUPT_CLEAR: u16 = 0x0086;
+const SYSTEM_MODE_START: u16 = 0x0087;
+const RESULT_RANGE_STATUS: u16 = 0x0089; // followed by the rest of the results
+const RESULT_OSC_CALIBRATE_VAL: u16 = 0x00de;
+const FIRMWARE_SYSTEM_STATUS: u16 = 0x00e5;
+const MODEL_ID: u16 = 0x010f;
+
+const ID: u16 = 0xeacc;
+const BOOT_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Written from 0x2d on by the ST ultra lite driver's init, long distance mode with a 100ms
+// timing budget and the interrupt active high on new data.
+#[rustfmt::skip]
+const DEFAULT_CONFIGURATION: [u8; 91] = [
+    0x00, 0x00, 0x00, 0x01, 0x02, 0x00, 0x02, 0x08, 0x00, 0x08, 0x10, 0x01, 0x01, 0x00, 0x00, 0x00,
+    0x00, 0xff, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x0b, 0x00, 0x00, 0x02, 0x0a, 0x21,
+    0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0xc8, 0x00, 0x00, 0x38, 0xff, 0x01, 0x00, 0x08, 0x00,
+    0x00, 0x01, 0xcc, 0x0f, 0x01, 0xf1, 0x0d, 0x01, 0x68, 0x00, 0x80, 0x08, 0xb8, 0x00, 0x00, 0x00,
+    0x00, 0x0f, 0x89, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x0f, 0x0d, 0x0e, 0x0e, 0x00,
+    0x00, 0x02, 0xc7, 0xff, 0x9b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+];
+
+// ST VL53L1X time of flight sensor, up to 4m.
+pub struct Vl53l1x<B: I2cBus> {
+    bus: B,
+    address: u8,
+    period: Duration,
+}
+
+impl<B: I2cBus> Vl53l1x<B> {
+    // `period` between measurements, at least the 100ms timing budget
+    pub fn new(bus: B, address: u8, period: Duration) -> Self {
+        Self {
+            bus,
+            address,
+            period,
+        }
+    }
+
+    fn init(&mut self) -> Result<(), TofError> {
+        let start = Instant::now();
+        while self.read_u8(FIRMWARE_SYSTEM_STATUS)? & 1 == 0 {
+            if start.elapsed() > BOOT_TIMEOUT {
+                return Err(TofError::Timeout);
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        let id = self.read_u16(MODEL_ID)?;
+        if id != ID {
+            return Err(TofError::WrongDevice(id));
+        }
+        self.write(DEFAULT_CONFIGURATION_START, &DEFAULT_CONFIGURATION)?;
+        // one measurement to run the VHV calibration, then keep its result for later starts
+        self.write(SYSTEM_MODE_START, &[0x40])?;
+        while !self.data_ready()? {
+            if start.elapsed() > BOOT_TIMEOUT + self.period {
+                return Err(TofError::Timeout);
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        self.write(SYSTEM_INTERRUPT_CLEAR, &[0x01])?;
+        self.write(SYSTEM_MODE_START, &[0x00])?;
+        self.write(VHV_CONFIG_TIMEOUT_MACROP_LOOP_BOUND, &[0x09])?;
+        self.write(VHV_CONFIG_INIT, &[0x00])?;
+        Ok(())
+    }
+
+    fn set_period(&mut self) -> Result<(), TofError> {
+        let clock_pll = (self.read_u16(RESULT_OSC_CALIBRATE_VAL)? & 0x3ff) as f32;
+        let period = (self.period.as_millis() as f32 * clock_pll * 1.075) as u32;
+        self.write(SYSTEM_INTERMEASUREMENT_PERIOD, &period.to_be_bytes())
+    }
+
+    // the interrupt is active high
+    fn data_ready(&mut self) -> Result<bool, TofError> {
+        Ok(self.read_u8(GPIO_TIO_HV_STATUS)? & 1 == 1)
+    }
+
+    fn read_u8(&mut self, register: u16) -> Result<u8, TofError> {
+        let mut buffer = [0u8; 1];
+        self.read(register, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn read_u16(&mut self, register: u16) -> Result<u16, TofError> {
+        let mut buffer = [0u8; 2];
+        self.read(register, &mut buffer)?;
+        Ok(u16::from_be_bytes(buffer))
+    }
+
+    fn read(&mut self, register: u16, buffer: &mut [u8]) -> Result<(), TofError> {
+        Ok(self
+            .bus
+            .write_read(self.address, &register.to_be_bytes(), buffer)?)
+    }
+
+    fn write(&mut self, register: u16, bytes: &[u8]) -> Result<(), TofError> {
+        let mut buffer = register.to_be_bytes().to_vec();
+        buffer.extend_from_slice(bytes);
+        Ok(self.bus.write(self.address, &buffer)?)
+    }
+}
+
+impl<B: I2cBus> RangeFinder for Vl53l1x<B> {
+    fn start(&mut self) -> Result<(), TofError> {
+        self.init()?;
+        self.set_period()?;
+        self.write(SYSTEM_INTERRUPT_CLEAR, &[0x01])?;
+        self.write(SYSTEM_MODE_START, &[0x40])
+    }
+
+    fn read(&mut self) -> Result<Option<Reading>, TofError> {
+        if !self.data_ready()? {
+            return Ok(None);
+        }
+        let mut results = [0u8; 17];
+        self.read(RESULT_RANGE_STATUS, &mut results)?;
+        self.write(SYSTEM_INTERRUPT_CLEAR, &[0x01])?;
+        // 9 is a valid range, anything else is a sigma, signal or wrap around failure
+        let valid = results[0] & 0x1f == 9;
+        let distance = u16::from_be_bytes([results[13], results[14]]);
+        Ok(Some(Reading {
+            distance: if valid { distance } else { 0 },
+            // peak signal rate in MCPS, 9.7 fixed point
+            strength: u16::from_be_bytes([results[15], results[16]]),
+        }))
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use hal::host::SimI2c;
+
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(100);
+
+    // a booted sensor with a measurement ready, the status and result registers keep what's set
+    fn sensor() -> (SimI2c, Vl53l1x<SimI2c>) {
+        let bus = SimI2c::new();
+        bus.add_device(VL53L1X_ADDRESS, 2);
+        bus.set_registers(VL53L1X_ADDRESS, FIRMWARE_SYSTEM_STATUS, &[1]);
+        bus.set_registers(VL53L1X_ADDRESS, MODEL_ID, &ID.to_be_bytes());
+        bus.set_registers(VL53L1X_ADDRESS, GPIO_TIO_HV_STATUS, &[1]);
+        bus.set_registers(VL53L1X_ADDRESS, RESULT_OSC_CALIBRATE_VAL, &[0x01, 0x00]);
+        bus.set_read_only(VL53L1X_ADDRESS, GPIO_TIO_HV_STATUS, 1);
+        bus.set_read_only(VL53L1X_ADDRESS, RESULT_RANGE_STATUS, 17);
+        let sensor = Vl53l1x::new(bus.clone(), VL53L1X_ADDRESS, PERIOD);
+        (bus, sensor)
+    }
+
+    fn result(bus: &SimI2c, status: u8, distance: u16, signal: u16) {
+        let mut results = [0u8; 17];
+        results[0] = status;
+        results[13..15].copy_from_slice(&distance.to_be_bytes());
+        results[15..17].copy_from_slice(&signal.to_be_bytes());
+        bus.set_registers(VL53L1X_ADDRESS, RESULT_RANGE_STATUS, &results);
+    }
+
+    #[test]
+    fn starts_ranging() {
+        let (bus, mut sensor) = sensor();
+        sensor.start().unwrap();
+        assert_eq!(
+            bus.registers(VL53L1X_ADDRESS, DEFAULT_CONFIGURATION_START, 4),
+            DEFAULT_CONFIGURATION[..4]
+        );
+        assert_eq!(bus.registers(VL53L1X_ADDRESS, VHV_CONFIG_INIT, 1), [0]);
+        // 100ms with a 256 oscillator calibration
+        let period = (100.0 * 256.0 * 1.075) as u32;
+        assert_eq!(
+            bus.registers(VL53L1X_ADDRESS, SYSTEM_INTERMEASUREMENT_PERIOD, 4),
+            period.to_be_bytes()
+        );
+        assert_eq!(bus.registers(VL53L1X_ADDRESS, SYSTEM_MODE_START, 1), [0x40]);
+    }
+
+    #[test]
+    fn wrong_device() {
+        let (bus, mut sensor) = sensor();
+        bus.set_registers(VL53L1X_ADDRESS, MODEL_ID, &[0x12, 0x34]);
+        assert!(matches!(sensor.start(), Err(TofError::WrongDevice(0x1234))));
+    }
+
+    #[test]
+    fn boot_timeout() {
+        let (bus, mut sensor) = sensor();
+        bus.set_registers(VL53L1X_ADDRESS, FIRMWARE_SYSTEM_STATUS, &[0]);
+        let start = Instant::now();
+        assert!(matches!(sensor.start(), Err(TofError::Timeout)));
+        assert!(start.elapsed() >= BOOT_TIMEOUT);
+    }
+
+    // the inherent `read` is the register read
+    #[test]
+    fn reads_the_range() {
+        let (bus, mut sensor) = sensor();
+        sensor.start().unwrap();
+        result(&bus, 9, 1234, 0x0280);
+        assert_eq!(
+            RangeFinder::read(&mut sensor).unwrap(),
+            Some(Reading {
+                distance: 1234,
+                strength: 0x0280,
+            })
+        );
+        // a signal failure
+        result(&bus, 2, 1234, 0x0010);
+        assert_eq!(RangeFinder::read(&mut sensor).unwrap().unwrap().distance, 0);
+        bus.set_registers(VL53L1X_ADDRESS, GPIO_TIO_HV_STATUS, &[0]);
+        assert_eq!(RangeFinder::read(&mut sensor).unwrap(), None);
+    }
+}
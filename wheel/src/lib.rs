@@ -5,6 +5,12 @@ use log::*;
 use position_control::PositionControl;
 use position_control::PositionControlCmd;
 use position_control::PositionControlError;
+use speed_control::SpeedControlCmd;
+
+pub use position_control::CommandId;
+pub use position_control::Completion;
+pub use position_control::Notifier;
+pub use position_control::Status;
 
 #[derive(Clone)]
 pub struct Wheel<'d> {
@@ -35,6 +41,11 @@ impl<'d> Wheel<'d> {
         (self.position.get_position() as f64 / self.degrees_per_mm) as i64
     }
 
+    // position in mm without rounding
+    pub fn get_distance(&self) -> f64 {
+        self.position.get_position() as f64 / self.degrees_per_mm
+    }
+
     pub fn is_active(&self) -> bool {
         self.position.is_active()
     }
@@ -44,10 +55,39 @@ impl<'d> Wheel<'d> {
         Ok(())
     }
 
-    pub fn set_position(&self, position: i64) -> Result<(), PositionControlError> {
-        let pos = (position as f64 * self.degrees_per_mm) as i64;
-        info!("set position: {pos} ({position})",);
-        self.position.send(PositionControlCmd::SetPosition(pos))?;
+    // the configured max speed in mm per second
+    pub fn max_speed(&self) -> f64 {
+        self.position.max_speed().abs() as f64 / self.degrees_per_mm
+    }
+
+    // speed in mm per second, None restores the default
+    pub fn set_max_speed(&self, speed: Option<f64>) -> Result<(), PositionControlError> {
+        let speed = speed.map(|s| (s * self.degrees_per_mm) as f32);
+        self.position.send(PositionControlCmd::SetMaxSpeed(speed))?;
+        Ok(())
+    }
+
+    // drive at a constant speed in mm per second, this stops any move in progress
+    pub fn set_speed(&self, speed: f64) -> Result<(), PositionControlError> {
+        let speed = (speed * self.degrees_per_mm) as f32;
+        self.position
+            .send(PositionControlCmd::SpeedCmd(SpeedControlCmd::SetSpeed(
+                speed,
+            )))?;
         Ok(())
     }
+
+    pub fn set_position(&self, position: i64) -> Result<CommandId, PositionControlError> {
+        let pos = (position as f64 * self.degrees_per_mm) as i64;
+        let id = CommandId::next();
+        info!("set position: {id} {pos} ({position})",);
+        self.position
+            .send(PositionControlCmd::SetPosition((id, pos)))?;
+        Ok(id)
+    }
+
+    // completions of the moves started with `set_position`
+    pub fn notifier(&self) -> &Notifier {
+        self.position.notifier()
+    }
 }